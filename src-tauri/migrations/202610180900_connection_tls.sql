@@ -0,0 +1,5 @@
+ALTER TABLE connection_configs ADD COLUMN tls_enabled BOOLEAN DEFAULT 0 NOT NULL;
+ALTER TABLE connection_configs ADD COLUMN tls_ca_cert_path TEXT;
+ALTER TABLE connection_configs ADD COLUMN tls_client_cert_path TEXT;
+ALTER TABLE connection_configs ADD COLUMN tls_client_key_path TEXT;
+ALTER TABLE connection_configs ADD COLUMN tls_skip_verify BOOLEAN DEFAULT 0 NOT NULL;
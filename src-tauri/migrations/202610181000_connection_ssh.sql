@@ -0,0 +1,6 @@
+ALTER TABLE connection_configs ADD COLUMN ssh_host TEXT;
+ALTER TABLE connection_configs ADD COLUMN ssh_port INTEGER;
+ALTER TABLE connection_configs ADD COLUMN ssh_username TEXT;
+ALTER TABLE connection_configs ADD COLUMN ssh_password TEXT;
+ALTER TABLE connection_configs ADD COLUMN ssh_private_key_path TEXT;
+ALTER TABLE connection_configs ADD COLUMN ssh_passphrase TEXT;
@@ -0,0 +1,6 @@
+ALTER TABLE connection_configs ADD COLUMN mode TEXT DEFAULT 'standalone' NOT NULL;
+ALTER TABLE connection_configs ADD COLUMN sentinel_master_name TEXT;
+ALTER TABLE connection_configs ADD COLUMN sentinel_nodes TEXT;
+ALTER TABLE connection_configs ADD COLUMN sentinel_username TEXT;
+ALTER TABLE connection_configs ADD COLUMN sentinel_password TEXT;
+ALTER TABLE connection_configs ADD COLUMN sentinel_read_from_replica BOOLEAN DEFAULT 0 NOT NULL;
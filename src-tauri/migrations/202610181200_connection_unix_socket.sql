@@ -0,0 +1 @@
+ALTER TABLE connection_configs ADD COLUMN socket_path TEXT;
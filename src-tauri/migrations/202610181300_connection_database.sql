@@ -0,0 +1 @@
+ALTER TABLE connection_configs ADD COLUMN database INTEGER DEFAULT 0 NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE connection_configs ADD COLUMN resp3 BOOLEAN DEFAULT 0 NOT NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE connection_configs ADD COLUMN connect_timeout_secs INTEGER;
+ALTER TABLE connection_configs ADD COLUMN response_timeout_secs INTEGER;
+ALTER TABLE connection_configs ADD COLUMN keepalive_secs INTEGER;
+ALTER TABLE connection_configs ADD COLUMN client_name TEXT;
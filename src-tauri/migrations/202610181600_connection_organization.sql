@@ -0,0 +1,4 @@
+ALTER TABLE connection_configs ADD COLUMN group_name TEXT;
+ALTER TABLE connection_configs ADD COLUMN tags TEXT;
+ALTER TABLE connection_configs ADD COLUMN color TEXT;
+ALTER TABLE connection_configs ADD COLUMN sort_order INTEGER DEFAULT 0 NOT NULL;
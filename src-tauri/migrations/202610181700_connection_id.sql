@@ -0,0 +1,3 @@
+ALTER TABLE connection_configs ADD COLUMN id INTEGER;
+UPDATE connection_configs SET id = rowid;
+CREATE UNIQUE INDEX connection_configs_id ON connection_configs (id);
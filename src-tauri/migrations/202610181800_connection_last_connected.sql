@@ -0,0 +1 @@
+ALTER TABLE connection_configs ADD COLUMN last_connected_at DATETIME;
@@ -0,0 +1,2 @@
+ALTER TABLE connection_configs ADD COLUMN read_only BOOLEAN DEFAULT 0 NOT NULL;
+ALTER TABLE connection_configs ADD COLUMN environment TEXT DEFAULT 'dev' NOT NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE connection_configs ADD COLUMN proxy_kind TEXT;
+ALTER TABLE connection_configs ADD COLUMN proxy_host TEXT;
+ALTER TABLE connection_configs ADD COLUMN proxy_port INTEGER;
+ALTER TABLE connection_configs ADD COLUMN proxy_username TEXT;
+ALTER TABLE connection_configs ADD COLUMN proxy_password TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE connection_configs ADD COLUMN key_delimiters TEXT;
+ALTER TABLE connection_configs ADD COLUMN flat_key_list BOOLEAN DEFAULT 0 NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE connection_configs ADD COLUMN tls_server_name TEXT;
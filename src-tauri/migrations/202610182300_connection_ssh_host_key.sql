@@ -0,0 +1 @@
+ALTER TABLE connection_configs ADD COLUMN ssh_host_key_fingerprint TEXT;
@@ -0,0 +1,11 @@
+-- Whether the secrets of a row are encrypted used to be read from their
+-- `enc:v1:` prefix, which is all there is to go by for existing rows.
+ALTER TABLE connection_configs ADD COLUMN secrets_encrypted BOOLEAN DEFAULT 0 NOT NULL;
+
+UPDATE connection_configs
+SET secrets_encrypted = 1
+WHERE password LIKE 'enc:v1:%'
+   OR ssh_password LIKE 'enc:v1:%'
+   OR ssh_passphrase LIKE 'enc:v1:%'
+   OR sentinel_password LIKE 'enc:v1:%'
+   OR proxy_password LIKE 'enc:v1:%';
@@ -0,0 +1,93 @@
+-- Connections in the trash give up their name, so only those outside it
+-- need unique names. SQLite cannot drop a constraint in place, so the table
+-- is rebuilt without it.
+CREATE TABLE connection_configs_new
+(
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    name      TEXT           NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    deleted_at DATETIME,
+    host      TEXT           NOT NULL,
+    port      INTEGER        NOT NULL,
+    username  TEXT           NOT NULL,
+    password  TEXT           NOT NULL,
+    tls_enabled BOOLEAN DEFAULT 0 NOT NULL,
+    tls_ca_cert_path TEXT,
+    tls_client_cert_path TEXT,
+    tls_client_key_path TEXT,
+    tls_skip_verify BOOLEAN DEFAULT 0 NOT NULL,
+    ssh_host TEXT,
+    ssh_port INTEGER,
+    ssh_username TEXT,
+    ssh_password TEXT,
+    ssh_private_key_path TEXT,
+    ssh_passphrase TEXT,
+    mode TEXT DEFAULT 'standalone' NOT NULL,
+    sentinel_master_name TEXT,
+    sentinel_nodes TEXT,
+    sentinel_username TEXT,
+    sentinel_password TEXT,
+    sentinel_read_from_replica BOOLEAN DEFAULT 0 NOT NULL,
+    socket_path TEXT,
+    database INTEGER DEFAULT 0 NOT NULL,
+    resp3 BOOLEAN DEFAULT 0 NOT NULL,
+    connect_timeout_secs INTEGER,
+    response_timeout_secs INTEGER,
+    keepalive_secs INTEGER,
+    client_name TEXT,
+    group_name TEXT,
+    tags TEXT,
+    color TEXT,
+    sort_order INTEGER DEFAULT 0 NOT NULL,
+    last_connected_at DATETIME,
+    read_only BOOLEAN DEFAULT 0 NOT NULL,
+    environment TEXT DEFAULT 'dev' NOT NULL,
+    proxy_kind TEXT,
+    proxy_host TEXT,
+    proxy_port INTEGER,
+    proxy_username TEXT,
+    proxy_password TEXT,
+    key_delimiters TEXT,
+    flat_key_list BOOLEAN DEFAULT 0 NOT NULL,
+    tls_server_name TEXT,
+    ssh_host_key_fingerprint TEXT,
+    secrets_encrypted BOOLEAN DEFAULT 0 NOT NULL
+);
+
+INSERT INTO connection_configs_new (
+    name, created_at, updated_at, deleted_at, host, port, username, password,
+    tls_enabled, tls_ca_cert_path, tls_client_cert_path, tls_client_key_path,
+    tls_skip_verify, ssh_host, ssh_port, ssh_username, ssh_password,
+    ssh_private_key_path, ssh_passphrase, mode, sentinel_master_name,
+    sentinel_nodes, sentinel_username, sentinel_password,
+    sentinel_read_from_replica, socket_path, database, resp3,
+    connect_timeout_secs, response_timeout_secs, keepalive_secs, client_name,
+    group_name, tags, color, sort_order, id, last_connected_at, read_only,
+    environment, proxy_kind, proxy_host, proxy_port, proxy_username,
+    proxy_password, key_delimiters, flat_key_list, tls_server_name,
+    ssh_host_key_fingerprint, secrets_encrypted
+)
+SELECT
+    name, created_at, updated_at, deleted_at, host, port, username, password,
+    tls_enabled, tls_ca_cert_path, tls_client_cert_path, tls_client_key_path,
+    tls_skip_verify, ssh_host, ssh_port, ssh_username, ssh_password,
+    ssh_private_key_path, ssh_passphrase, mode, sentinel_master_name,
+    sentinel_nodes, sentinel_username, sentinel_password,
+    sentinel_read_from_replica, socket_path, database, resp3,
+    connect_timeout_secs, response_timeout_secs, keepalive_secs, client_name,
+    group_name, tags, color, sort_order, id, last_connected_at, read_only,
+    environment, proxy_kind, proxy_host, proxy_port, proxy_username,
+    proxy_password, key_delimiters, flat_key_list, tls_server_name,
+    ssh_host_key_fingerprint, secrets_encrypted
+FROM connection_configs;
+
+-- Ids of purged connections are not handed out again.
+UPDATE sqlite_sequence
+SET seq = (SELECT seq FROM sqlite_sequence WHERE name = 'connection_configs')
+WHERE name = 'connection_configs_new';
+
+DROP TABLE connection_configs;
+ALTER TABLE connection_configs_new RENAME TO connection_configs;
+
+CREATE UNIQUE INDEX connection_configs_name ON connection_configs (name) WHERE deleted_at IS NULL;
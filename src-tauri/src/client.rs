@@ -0,0 +1,501 @@
+use std::time::Duration;
+
+use log::warn;
+use redis::{
+    aio::{ConnectionLike, MultiplexedConnection},
+    cluster::ClusterClientBuilder,
+    cluster_async::ClusterConnection,
+    cluster_routing::{RoutingInfo, SingleNodeRoutingInfo},
+    AsyncConnectionConfig, Client, ClientTlsConfig, Cmd, ConnectionAddr, ConnectionInfo, ErrorKind,
+    FromRedisValue, Pipeline, ProtocolVersion, RedisConnectionInfo, RedisError, RedisFuture,
+    RedisResult, TlsCertificates, Value,
+};
+use tadis_database::models::{ConnectionConfig, ConnectionMode};
+
+use crate::proxy::ProxyTunnel;
+use crate::sentinel;
+use crate::tls;
+use crate::tunnel::{SshTunnel, Tunnel};
+
+/// A live connection to either a single server or a whole cluster.
+///
+/// Both variants multiplex commands over shared connections, so clones are
+/// cheap handles that can run commands concurrently.
+#[derive(Clone)]
+pub enum RedisConnection {
+    Single(MultiplexedConnection),
+    Cluster(ClusterConnection),
+}
+
+impl RedisConnection {
+    pub fn is_cluster(&self) -> bool {
+        matches!(self, RedisConnection::Cluster(_))
+    }
+
+    /// Runs a keyless command on one node. On a cluster the node is picked at
+    /// random instead of fanning out, so the reply keeps its single-node shape.
+    pub async fn query_one_node<T: FromRedisValue>(&mut self, cmd: &Cmd) -> RedisResult<T> {
+        match self {
+            RedisConnection::Single(connection) => cmd.query_async(connection).await,
+            RedisConnection::Cluster(connection) => {
+                let value = connection
+                    .route_command(cmd, RoutingInfo::SingleNode(SingleNodeRoutingInfo::Random))
+                    .await?;
+                T::from_redis_value(&value)
+            }
+        }
+    }
+
+    /// Runs a keyless command on the cluster node at `node` (`ip:port`), or on
+    /// the server itself when `node` is `None` or this is not a cluster.
+    pub async fn query_node<T: FromRedisValue>(
+        &mut self,
+        cmd: &Cmd,
+        node: Option<&str>,
+    ) -> RedisResult<T> {
+        match (self, node.and_then(node_address)) {
+            (RedisConnection::Cluster(connection), Some(routing)) => {
+                let value = connection
+                    .route_command(cmd, RoutingInfo::SingleNode(routing))
+                    .await?;
+                T::from_redis_value(&value)
+            }
+            (connection, _) => connection.query_one_node(cmd).await,
+        }
+    }
+
+    /// Sends `pipe` to the cluster node at `node` (`ip:port`) in one round
+    /// trip. Without a node, or when this is not a cluster, it goes where its
+    /// first key leads.
+    pub async fn query_pipeline_node(
+        &mut self,
+        pipe: &Pipeline,
+        node: Option<&str>,
+    ) -> RedisResult<Vec<Value>> {
+        let count = pipe.cmd_iter().count();
+        match (self, node.and_then(node_address)) {
+            (RedisConnection::Cluster(connection), Some(routing)) => {
+                connection.route_pipeline(pipe, 0, count, routing).await
+            }
+            (connection, _) => connection.req_packed_commands(pipe, 0, count).await,
+        }
+    }
+
+    /// Sends a PING, giving up after `timeout` instead of waiting on a socket
+    /// that silently stopped answering.
+    pub async fn ping(&mut self, timeout: Duration) -> bool {
+        let ping = redis::cmd("PING");
+        let reply = tokio::time::timeout(timeout, ping.query_async::<String>(self)).await;
+        matches!(reply, Ok(Ok(_)))
+    }
+}
+
+/// Routes to the node at `ip:port`.
+fn node_address(node: &str) -> Option<SingleNodeRoutingInfo> {
+    let (host, port) = node.rsplit_once(':')?;
+    Some(SingleNodeRoutingInfo::ByAddress {
+        host: host.to_string(),
+        port: port.parse().ok()?,
+    })
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Single(connection) => connection.req_packed_command(cmd),
+            RedisConnection::Cluster(connection) => connection.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Single(connection) => {
+                connection.req_packed_commands(cmd, offset, count)
+            }
+            RedisConnection::Cluster(connection) => {
+                connection.req_packed_commands(cmd, offset, count)
+            }
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Single(connection) => connection.get_db(),
+            RedisConnection::Cluster(connection) => connection.get_db(),
+        }
+    }
+}
+
+/// Opens the SSH tunnel or proxy forward, if one is configured, and the
+/// connection through it.
+pub async fn open_connection(
+    config: &ConnectionConfig,
+) -> Result<(RedisConnection, Option<Tunnel>), String> {
+    let tunnel = {
+        let config = config.clone();
+        run_blocking(move || open_tunnel(&config)).await?
+    };
+    let connection = connect(config, tunnel.as_ref().map(Tunnel::local_port)).await?;
+    Ok((connection, tunnel))
+}
+
+/// Opens the connection described by `config`, through the local end of a
+/// tunnel if `tunnel_port` is set, and names it after the configured client.
+pub async fn connect(
+    config: &ConnectionConfig,
+    tunnel_port: Option<u16>,
+) -> Result<RedisConnection, String> {
+    // Sentinels and cluster nodes are reached through redis-rs, which can only
+    // check the whole certificate against the address it connects to.
+    if config.tls_enabled
+        && matches!(
+            config.mode,
+            ConnectionMode::Sentinel | ConnectionMode::Cluster
+        )
+        && (config.tls_skip_verify || non_empty(&config.tls_server_name).is_some())
+    {
+        return Err(
+            "Skipping TLS hostname verification and a TLS server name are only supported for standalone connections"
+                .to_string(),
+        );
+    }
+
+    let mut connection = if config.mode == ConnectionMode::Cluster {
+        connect_cluster(config).await?
+    } else {
+        connect_single(config, tunnel_port).await?
+    };
+
+    // Routed to every node of a cluster. Nodes discovered later keep no name.
+    // Only a label, so an ACL user without the permission still connects.
+    if let Err(e) = redis::cmd("CLIENT")
+        .arg("SETNAME")
+        .arg(config.client_name())
+        .exec_async(&mut connection)
+        .await
+    {
+        warn!(
+            "Failed to set client name '{}': {}",
+            config.client_name(),
+            e
+        );
+    }
+
+    Ok(connection)
+}
+
+async fn connect_single(
+    config: &ConnectionConfig,
+    tunnel_port: Option<u16>,
+) -> Result<RedisConnection, String> {
+    let mut connection_config =
+        AsyncConnectionConfig::new().set_connection_timeout(config.connect_timeout());
+    if let Some(response_timeout) = config.response_timeout() {
+        connection_config = connection_config.set_response_timeout(response_timeout);
+    }
+
+    if config.tls_enabled && config.mode != ConnectionMode::Unix {
+        return connect_tls(config, tunnel_port, connection_config).await;
+    }
+
+    // Asking sentinels blocks.
+    let client = {
+        let config = config.clone();
+        run_blocking(move || build_client(&config, tunnel_port)).await?
+    };
+
+    client
+        .get_multiplexed_async_connection_with_config(&connection_config)
+        .await
+        .map(RedisConnection::Single)
+        .map_err(|e| connect_error(config, e))
+}
+
+/// Starts TLS here rather than in redis-rs, which can only verify the server
+/// certificate fully or not at all, and always against the address it
+/// connects to.
+async fn connect_tls(
+    config: &ConnectionConfig,
+    tunnel_port: Option<u16>,
+    connection_config: AsyncConnectionConfig,
+) -> Result<RedisConnection, String> {
+    // Asking sentinels and reading certificates both block.
+    let ((host, port), certificates) = {
+        let config = config.clone();
+        run_blocking(move || {
+            Ok((
+                target(&config, tunnel_port)?,
+                load_tls_certificates(&config)?,
+            ))
+        })
+        .await?
+    };
+    // Through a tunnel the server is reached on the local end of the forward,
+    // but still verified under its own name.
+    let server_name = match non_empty(&config.tls_server_name) {
+        Some(server_name) => server_name,
+        None if tunnel_port.is_some() => &config.host,
+        None => &host,
+    };
+    let info = redis_connection_info(config, &config.username, &config.password, config.database);
+
+    let open = async {
+        let stream = tls::connect(
+            &host,
+            port,
+            server_name,
+            certificates,
+            config.tls_skip_verify,
+        )
+        .await?;
+        MultiplexedConnection::new_with_config(&info, stream, connection_config)
+            .await
+            .map_err(|e| connect_error(config, e))
+    };
+    let (connection, driver) = tokio::time::timeout(config.connect_timeout(), open)
+        .await
+        .map_err(|_| "Failed to connect to Redis: connection timed out".to_string())??;
+    tauri::async_runtime::spawn(driver);
+
+    Ok(RedisConnection::Single(connection))
+}
+
+fn open_tunnel(config: &ConnectionConfig) -> Result<Option<Tunnel>, String> {
+    match (&config.ssh, &config.proxy) {
+        (Some(_), Some(_)) => {
+            Err("A connection can use an SSH tunnel or a proxy, not both".to_string())
+        }
+        // Sentinels and cluster nodes hand out addresses of other servers,
+        // which a single port forward cannot follow.
+        (Some(_), None) if config.mode != ConnectionMode::Standalone => {
+            Err("SSH tunnels are only supported for standalone connections".to_string())
+        }
+        (None, Some(_)) if config.mode != ConnectionMode::Standalone => {
+            Err("Proxies are only supported for standalone connections".to_string())
+        }
+        (Some(ssh), None) => SshTunnel::open(ssh, &config.host, config.port)
+            .map(|tunnel| Some(Tunnel::Ssh(tunnel)))
+            .map_err(|e| format!("SSH connection failed: {}", e)),
+        (None, Some(proxy)) => {
+            ProxyTunnel::open(proxy, &config.host, config.port, config.connect_timeout())
+                .map(|tunnel| Some(Tunnel::Proxy(tunnel)))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+fn build_client(config: &ConnectionConfig, tunnel_port: Option<u16>) -> Result<Client, String> {
+    if config.mode == ConnectionMode::Unix {
+        return build_unix_client(config);
+    }
+
+    let (host, port) = target(config, tunnel_port)?;
+    build_client_for(
+        config,
+        &host,
+        port,
+        &config.username,
+        &config.password,
+        config.database,
+    )
+}
+
+/// The address the server is reached at: the master named by the sentinels,
+/// the local end of a tunnel, or the configured one.
+fn target(config: &ConnectionConfig, tunnel_port: Option<u16>) -> Result<(String, u16), String> {
+    match (config.mode, tunnel_port) {
+        (ConnectionMode::Sentinel, _) => sentinel::resolve_target(config),
+        (_, Some(tunnel_port)) => Ok(("127.0.0.1".to_string(), tunnel_port)),
+        (_, None) => Ok((config.host.clone(), config.port)),
+    }
+}
+
+/// Builds a client for an explicit address, credentials and database,
+/// keeping the TLS settings of `config`.
+pub fn build_client_for(
+    config: &ConnectionConfig,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    database: i64,
+) -> Result<Client, String> {
+    // Built directly rather than as a URL so credentials containing `@`, `:`
+    // or `/` need no escaping.
+    let addr = if config.tls_enabled {
+        ConnectionAddr::TcpTls {
+            host: host.to_string(),
+            port,
+            insecure: false,
+            tls_params: None,
+        }
+    } else {
+        ConnectionAddr::Tcp(host.to_string(), port)
+    };
+    let info = ConnectionInfo {
+        addr,
+        redis: redis_connection_info(config, username, password, database),
+    };
+
+    if !config.tls_enabled {
+        return Client::open(info).map_err(|e| format!("Failed to create Redis client: {}", e));
+    }
+
+    // The host doubles as the SNI name, so it must match the server certificate.
+    Client::build_with_tls(info, load_tls_certificates(config)?)
+        .map_err(|e| format!("Failed to create Redis client: {}", e))
+}
+
+/// Credentials, database and protocol sent while connecting. A username means
+/// ACL `AUTH username password`, a password alone means legacy `AUTH password`
+/// and neither sends no AUTH at all. With RESP3 the credentials ride along
+/// with `HELLO 3` instead.
+fn redis_connection_info(
+    config: &ConnectionConfig,
+    username: &str,
+    password: &str,
+    database: i64,
+) -> RedisConnectionInfo {
+    let username = Some(username.to_string()).filter(|u| !u.is_empty());
+    // ACL users created with `nopass` still authenticate, with any password.
+    let password = Some(password.to_string()).filter(|p| !p.is_empty() || username.is_some());
+
+    RedisConnectionInfo {
+        db: database,
+        username,
+        password,
+        protocol: protocol_version(config),
+    }
+}
+
+fn protocol_version(config: &ConnectionConfig) -> ProtocolVersion {
+    if config.resp3 {
+        ProtocolVersion::RESP3
+    } else {
+        ProtocolVersion::RESP2
+    }
+}
+
+/// Tells failed authentication and a server without RESP3 apart from other
+/// connection errors, since the fix for each lives in the connection settings.
+fn connect_error(config: &ConnectionConfig, e: RedisError) -> String {
+    if e.kind() == ErrorKind::AuthenticationFailed
+        || matches!(e.code(), Some("WRONGPASS") | Some("NOAUTH"))
+    {
+        format!("Authentication failed: {}", e)
+    } else if config.resp3 && e.to_string().contains("HELLO") {
+        format!(
+            "Server does not support RESP3, turn it off for this connection: {}",
+            e
+        )
+    } else {
+        format!("Failed to connect to Redis: {}", e)
+    }
+}
+
+/// Builds a `redis+unix://` client for the configured socket. TLS settings do
+/// not apply to local sockets and are ignored.
+fn build_unix_client(config: &ConnectionConfig) -> Result<Client, String> {
+    if !cfg!(unix) {
+        return Err("Unix socket connections are not supported on this platform".to_string());
+    }
+
+    let socket_path = non_empty(&config.socket_path)
+        .ok_or(format!("Connection '{}' needs a socket path", config.name))?;
+
+    // Built directly rather than as a URL so the path and credentials need no
+    // escaping.
+    let info = ConnectionInfo {
+        addr: ConnectionAddr::Unix(socket_path.into()),
+        redis: redis_connection_info(config, &config.username, &config.password, config.database),
+    };
+
+    Client::open(info).map_err(|e| format!("Failed to create Redis client: {}", e))
+}
+
+async fn connect_cluster(config: &ConnectionConfig) -> Result<RedisConnection, String> {
+    if config.database != 0 {
+        return Err("Cluster connections only support database 0".to_string());
+    }
+
+    let scheme = if config.tls_enabled { "rediss" } else { "redis" };
+    let seed = format!("{}://{}:{}", scheme, config.host, config.port);
+
+    let credentials = redis_connection_info(config, &config.username, &config.password, 0);
+    let mut builder = ClusterClientBuilder::new(vec![seed])
+        .connection_timeout(config.connect_timeout())
+        .use_protocol(credentials.protocol);
+    if let Some(response_timeout) = config.response_timeout() {
+        builder = builder.response_timeout(response_timeout);
+    }
+    // Replica connections are opened with READONLY so reads can go to them.
+    if config.read_only {
+        builder = builder.read_from_replicas();
+    }
+    if let Some(username) = credentials.username {
+        builder = builder.username(username);
+    }
+    if let Some(password) = credentials.password {
+        builder = builder.password(password);
+    }
+    if config.tls_enabled {
+        builder = builder.certs(load_tls_certificates(config)?);
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to create Redis client: {}", e))?
+        .get_async_connection()
+        .await
+        .map(RedisConnection::Cluster)
+        .map_err(|e| connect_error(config, e))
+}
+
+fn load_tls_certificates(config: &ConnectionConfig) -> Result<TlsCertificates, String> {
+    let root_cert = match non_empty(&config.tls_ca_cert_path) {
+        Some(path) => Some(read_pem(path, "CA certificate")?),
+        None => None,
+    };
+
+    let client_tls = match (
+        non_empty(&config.tls_client_cert_path),
+        non_empty(&config.tls_client_key_path),
+    ) {
+        (Some(cert_path), Some(key_path)) => Some(ClientTlsConfig {
+            client_cert: read_pem(cert_path, "client certificate")?,
+            client_key: read_pem(key_path, "client key")?,
+        }),
+        (None, None) => None,
+        _ => {
+            return Err(
+                "Client certificate and client key must be provided together".to_string(),
+            )
+        }
+    };
+
+    Ok(TlsCertificates {
+        client_tls,
+        root_cert,
+    })
+}
+
+async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Background task failed: {}", e))?
+}
+
+fn read_pem(path: &str, description: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {} '{}': {}", description, path, e))
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
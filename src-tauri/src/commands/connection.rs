@@ -1,42 +1,79 @@
-use std::{sync::Mutex, time::Duration};
+use std::sync::Mutex;
 
-use log::info;
+use log::{info, warn};
 use tadis_database::{
-    models::ConnectionConfig,
+    crypto::Credentials,
+    models::{ConnectionConfig, ConnectionMode},
     queries::{
         connection_name_exists, create_connection_configuration, delete_connection_configuration,
-        get_all_connection_configurations, update_connection_configuration,
+        get_all_connection_configurations, get_connection_configuration,
+        get_connection_configuration_by_id, get_deleted_connection_configurations,
+        purge_connection_configurations, reorder_connection_configurations,
+        restore_connection_configuration, touch_connection_last_connected,
+        update_connection_configuration,
     },
 };
-use redis::Client;
-use tauri::State;
+use tauri::{async_runtime::Mutex as AsyncMutex, Manager, State, Webview};
 
-use crate::AppState;
+use crate::client::open_connection;
+use crate::key_cache::KeyCache;
+use crate::models::connection::{
+    ActiveConnection, ExportFormat, ImportFailure, ImportReport, MergeStrategy,
+};
+use crate::{export, import};
+use crate::sentinel::SentinelWatcher;
+use crate::{AppState, LiveConnection};
 
+/// Creates or updates a connection and returns its id. Updates find the
+/// stored connection by `config.id`, so they can rename it.
 #[tauri::command]
 pub async fn save_connection_config(
     app_handle: tauri::AppHandle,
     config: ConnectionConfig,
     is_new: bool,
-) -> Result<(), String> {
+) -> Result<i64, String> {
     if is_new {
-        info!("Creating new connection configuration for {:?}", config);
+        info!("Creating new connection configuration '{}'", config.name);
 
-        let exists = connection_name_exists(&app_handle, config.name.clone()).await.map_err(|e| e.to_string())?;
-        if exists {
-            return Err(format!("Connection name '{}' already exists", config.name));
-        }
+        name_available(&app_handle, &config.name).await?;
 
         create_connection_configuration(&app_handle, config)
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| e.to_string())
     } else {
-        info!("Updating connection configuration for {:?}", config);
-        update_connection_configuration(&app_handle, config)
+        info!("Updating connection configuration '{}'", config.name);
+        let id = config
+            .id
+            .ok_or("Connection has not been saved yet".to_string())?;
+        let previous = get_connection_configuration_by_id(&app_handle, id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or(format!("Connection '{}' no longer exists", config.name))?;
+
+        let renamed = previous.name != config.name;
+        if renamed {
+            name_available(&app_handle, &config.name).await?;
+        }
+
+        let name = config.name.clone();
+        let settings_changed = connection_settings_changed(&previous, &config);
+        update_connection_configuration(&app_handle, config.clone())
             .await
             .map_err(|e| e.to_string())?;
+
+        if renamed {
+            info!("Renamed connection '{}' to '{}'", previous.name, name);
+            rename_live_connection(&app_handle, &previous.name, &name)?;
+        }
+        if settings_changed {
+            // The live connection was opened with the old settings.
+            close_connection(&app_handle, &name)?;
+        } else {
+            update_live_config(&app_handle, config)?;
+        }
+
+        Ok(id)
     }
-    Ok(())
 }
 
 #[tauri::command]
@@ -50,61 +87,540 @@ pub async fn load_connection_config(
     Ok(configs)
 }
 
+/// Saves the manual order of the connection list, `names` from top to bottom.
+#[tauri::command]
+pub async fn reorder_connection_configs(
+    app_handle: tauri::AppHandle,
+    names: Vec<String>,
+) -> Result<(), String> {
+    reorder_connection_configurations(&app_handle, &names)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn delete_connection_config(
     app_handle: tauri::AppHandle,
     connection_name: String,
 ) -> Result<(), String> {
-    delete_connection_configuration(&app_handle, connection_name)
+    delete_connection_configuration(&app_handle, connection_name.clone())
         .await
         .map_err(|e| e.to_string())?;
 
+    close_connection(&app_handle, &connection_name)?;
+
     Ok(())
 }
 
+/// The connections in the trash, which `delete_connection_config` moves them to.
+#[tauri::command]
+pub async fn load_deleted_connection_configs(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<ConnectionConfig>, String> {
+    get_deleted_connection_configurations(&app_handle)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Takes a connection out of the trash. Trashed connections give up their
+/// name, so this fails while another connection has it.
+#[tauri::command]
+pub async fn restore_connection_config(
+    app_handle: tauri::AppHandle,
+    connection_id: i64,
+) -> Result<(), String> {
+    let config = get_connection_configuration_by_id(&app_handle, connection_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .filter(|config| config.deleted_at.is_some())
+        .ok_or("Connection is not in the trash".to_string())?;
+    let taken = connection_name_exists(&app_handle, config.name.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    if taken {
+        return Err(format!(
+            "Another connection is named '{}', rename it before restoring this one",
+            config.name
+        ));
+    }
+
+    restore_connection_configuration(&app_handle, connection_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Permanently deletes a connection from the trash, or empties the trash
+/// when `connection_id` is `None`.
+#[tauri::command]
+pub async fn purge_connection_configs(
+    app_handle: tauri::AppHandle,
+    connection_id: Option<i64>,
+) -> Result<(), String> {
+    purge_connection_configurations(&app_handle, connection_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Creates a connection for every entry of `source`, which may hold one of
+/// our exports, `redis://` URIs or another Redis GUI's JSON export. Names that
+/// are already taken are handled according to `strategy`.
 #[tauri::command]
-pub async fn test_connection(config: ConnectionConfig) -> Result<String, String> {
-    let url = format!(
-        "redis://{}:{}@{}:{}/{}",
-        config.username, config.password, config.host, config.port, 0
+pub async fn import_connections(
+    app_handle: tauri::AppHandle,
+    source: String,
+    strategy: Option<MergeStrategy>,
+    passphrase: Option<String>,
+) -> Result<ImportReport, String> {
+    let strategy = strategy.unwrap_or_default();
+    let mut report = ImportReport::default();
+
+    for (index, entry) in import::parse(&source, passphrase.as_deref())?
+        .into_iter()
+        .enumerate()
+    {
+        let result = match entry {
+            Ok(config) => merge_connection(&app_handle, config, strategy, &mut report).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            report.failed.push(ImportFailure {
+                entry: index + 1,
+                error,
+            });
+        }
+    }
+
+    info!(
+        "Imported {} connection(s), overwrote {}, {} conflict(s), {} failure(s)",
+        report.imported.len(),
+        report.overwritten.len(),
+        report.conflicts.len(),
+        report.failed.len()
     );
 
-    let client = Client::open(url).map_err(|e| format!("Failed to create Redis client: {}", e))?;
+    Ok(report)
+}
+
+/// Serializes the named connections, or all of them when `names` is empty,
+/// into a file that `import_connections` reads back. Secrets are only
+/// included when a `passphrase` is given to encrypt them with.
+#[tauri::command]
+pub async fn export_connections(
+    app_handle: tauri::AppHandle,
+    names: Vec<String>,
+    format: ExportFormat,
+    passphrase: Option<String>,
+) -> Result<String, String> {
+    let mut configs = get_all_connection_configurations(&app_handle)
+        .await
+        .map_err(|e| e.to_string())?;
 
-    let _connection = client
-        .get_connection_with_timeout(Duration::from_secs(1))
-        .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+    if !names.is_empty() {
+        if let Some(missing) = names
+            .iter()
+            .find(|name| !configs.iter().any(|config| &config.name == *name))
+        {
+            return Err(format!("Connection '{}' does not exist", missing));
+        }
+        configs.retain(|config| names.contains(&config.name));
+    }
+
+    let credentials = app_handle.state::<AsyncMutex<Credentials>>();
+    let credentials = credentials.lock().await;
+    export::export(
+        configs,
+        &credentials,
+        format,
+        passphrase.as_deref().filter(|p| !p.is_empty()),
+    )
+}
+
+#[tauri::command]
+pub async fn test_connection(
+    app_handle: tauri::AppHandle,
+    config: ConnectionConfig,
+) -> Result<String, String> {
+    let stored = stored_config(&app_handle, &config).await?;
+    let config = decrypt_secrets(&app_handle, config, stored.as_ref()).await?;
+    let _connection = open_connection(&config).await?;
 
     Ok("Connection successful!".to_string())
 }
 
 #[tauri::command]
-pub fn connect_to_redis(
+pub async fn connect_to_redis(
+    app_handle: tauri::AppHandle,
     state: State<'_, Mutex<AppState>>,
-    config: ConnectionConfig,
+    webview: Webview,
+    mut config: ConnectionConfig,
 ) -> Result<(), String> {
-    let url = format!(
-        "redis://{}:{}@{}:{}/{}",
-        config.username, config.password, config.host, config.port, 0
-    );
+    if is_connected(&state, &config.name)? {
+        return Err(format!("Connection '{}' already connected.", config.name));
+    }
+    let stored = stored_config(&app_handle, &config)
+        .await?
+        .filter(|stored| stored.deleted_at.is_none())
+        .ok_or(format!("Connection '{}' is not saved", config.name))?;
+    // The protection comes from the saved connection, so the UI cannot lift it.
+    config.read_only = stored.read_only;
+    config.environment = stored.environment;
+    let config = decrypt_secrets(&app_handle, config, Some(&stored)).await?;
+
+    // Connecting can take a while, so the state stays unlocked until the
+    // connection is ready.
+    let (client, tunnel) = open_connection(&config).await?;
+
+    {
+        let mut state = state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?;
+        if state.connections.contains_key(&config.name) {
+            return Err(format!("Connection '{}' already connected.", config.name));
+        }
+
+        let sentinel_watcher = (config.mode == ConnectionMode::Sentinel)
+            .then(|| SentinelWatcher::spawn(app_handle.clone(), config.clone()));
+        state.connections.insert(
+            config.name.clone(),
+            LiveConnection {
+                client,
+                database: config.database,
+                config: config.clone(),
+                tunnel,
+                sentinel_watcher,
+                key_cache: KeyCache::default(),
+            },
+        );
+        state
+            .selected_clients
+            .insert(webview.label().to_string(), config.name.clone());
+        info!("Connected to Redis: {}", config.name);
+    }
+
+    // Only shown in the connection list, so failing to record it does not fail
+    // the connect.
+    if let Err(e) = touch_connection_last_connected(&app_handle, config.name.clone()).await {
+        warn!("Failed to record when '{}' was last connected: {}", config.name, e);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn select_database(
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
+    database: i64,
+) -> Result<(), String> {
+    let (selected, mut client) = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .resolve_client(connection_id.as_deref(), webview.label())?;
+
+    if client.is_cluster() && database != 0 {
+        return Err("Cluster connections only support database 0".to_string());
+    }
+
+    // Every handle shares the connection, so this switches all of them.
+    redis::cmd("SELECT")
+        .arg(database)
+        .exec_async(&mut client)
+        .await
+        .map_err(|e| format!("Failed to select database {}: {}", database, e))?;
+
     let mut state = state
         .lock()
         .map_err(|e| format!("Failed to lock state: {}", e))?;
-    if state.connected_clients.contains_key(&config.name) {
-        return Err(format!("Connection '{}' already connected.", config.name));
+    if let Some(live) = state.connections.get_mut(&selected) {
+        live.database = database;
+        live.key_cache.clear();
     }
 
-    let client = Client::open(url).map_err(|e| format!("Failed to create Redis client: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn disconnect(state: State<'_, Mutex<AppState>>, connection_name: String) -> Result<(), String> {
+    let mut state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if !state.remove_connection(&connection_name) {
+        return Err(format!("Connection '{}' is not connected.", connection_name));
+    }
+    info!("Disconnected from Redis: {}", connection_name);
 
-    let connection = client
-        .get_connection_with_timeout(Duration::from_secs(120))
-        .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+    Ok(())
+}
 
+#[tauri::command]
+pub fn switch_connection(
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_name: String,
+) -> Result<(), String> {
+    let mut state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if !state.connections.contains_key(&connection_name) {
+        return Err(format!("Connection '{}' is not connected.", connection_name));
+    }
     state
-        .connected_clients
-        .insert(config.name.clone(), connection);
-    state.selected_client = config.name.clone();
-    println!("Connected to Redis: {}", config.name);
+        .selected_clients
+        .insert(webview.label().to_string(), connection_name);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_active_connections(
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+) -> Result<Vec<ActiveConnection>, String> {
+    let state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let selected = state.selected_clients.get(webview.label());
+
+    let mut connections: Vec<ActiveConnection> = state
+        .connections
+        .iter()
+        .map(|(name, live)| ActiveConnection {
+            name: name.clone(),
+            database: live.database,
+            cluster: live.client.is_cluster(),
+            read_only: live.config.read_only,
+            environment: live.config.environment,
+            selected: selected == Some(name),
+        })
+        .collect();
+    connections.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(connections)
+}
+
+fn is_connected(state: &Mutex<AppState>, name: &str) -> Result<bool, String> {
+    let state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    Ok(state.connections.contains_key(name))
+}
+
+/// The saved connection `config` was loaded from, unless it was not saved.
+async fn stored_config(
+    app_handle: &tauri::AppHandle,
+    config: &ConnectionConfig,
+) -> Result<Option<ConnectionConfig>, String> {
+    match config.id {
+        Some(id) => get_connection_configuration_by_id(app_handle, id)
+            .await
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Decrypts the secrets in `config`, as sent by the UI for the `stored`
+/// connection, for opening a connection. The result stays in the backend.
+async fn decrypt_secrets(
+    app_handle: &tauri::AppHandle,
+    mut config: ConnectionConfig,
+    stored: Option<&ConnectionConfig>,
+) -> Result<ConnectionConfig, String> {
+    app_handle
+        .state::<AsyncMutex<Credentials>>()
+        .lock()
+        .await
+        .decrypt_submitted(&mut config, stored)?;
+
+    Ok(config)
+}
+
+async fn merge_connection(
+    app_handle: &tauri::AppHandle,
+    mut config: ConnectionConfig,
+    strategy: MergeStrategy,
+    report: &mut ImportReport,
+) -> Result<(), String> {
+    let existing = get_connection_configuration(app_handle, config.name.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match (existing, strategy) {
+        (None, _) => {
+            let name = config.name.clone();
+            create_connection_configuration(app_handle, config)
+                .await
+                .map_err(|e| e.to_string())?;
+            report.imported.push(name);
+        }
+        (Some(_), MergeStrategy::Skip) => report.conflicts.push(config.name),
+        (Some(existing), MergeStrategy::Overwrite) => {
+            config.id = existing.id;
+            keep_missing_secrets(&mut config, existing);
+            let name = config.name.clone();
+            update_connection_configuration(app_handle, config)
+                .await
+                .map_err(|e| e.to_string())?;
+            close_connection(app_handle, &name)?;
+            report.overwritten.push(name);
+        }
+        (Some(_), MergeStrategy::Rename) => {
+            config.name = free_name(app_handle, &config.name).await?;
+            let name = config.name.clone();
+            create_connection_configuration(app_handle, config)
+                .await
+                .map_err(|e| e.to_string())?;
+            report.imported.push(name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Exports can leave secrets out, which must not wipe the stored ones.
+fn keep_missing_secrets(config: &mut ConnectionConfig, existing: ConnectionConfig) {
+    if config.password.is_empty() {
+        config.password = existing.password;
+    }
+    if let (Some(ssh), Some(existing)) = (config.ssh.as_mut(), existing.ssh) {
+        ssh.password = ssh.password.take().or(existing.password);
+        ssh.passphrase = ssh.passphrase.take().or(existing.passphrase);
+    }
+    if let (Some(sentinel), Some(existing)) = (config.sentinel.as_mut(), existing.sentinel) {
+        sentinel.password = sentinel.password.take().or(existing.password);
+    }
+    if let (Some(proxy), Some(existing)) = (config.proxy.as_mut(), existing.proxy) {
+        proxy.password = proxy.password.take().or(existing.password);
+    }
+}
+
+/// The first of `name (2)`, `name (3)`, ... that is not taken.
+async fn free_name(app_handle: &tauri::AppHandle, name: &str) -> Result<String, String> {
+    for suffix in 2.. {
+        let candidate = format!("{} ({})", name, suffix);
+        let exists = connection_name_exists(app_handle, candidate.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Ok(candidate);
+        }
+    }
+    unreachable!()
+}
+
+/// Fails if a connection outside the trash has `name`.
+async fn name_available(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+    let exists = connection_name_exists(app_handle, name.to_string())
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists {
+        return Err(format!("Connection name '{}' already exists", name));
+    }
+
+    Ok(())
+}
+
+/// Whether saving `config` over `previous` changes how the server is reached.
+/// The name, the fields that only organise the list or show keys, and the
+/// timestamps do not.
+fn connection_settings_changed(previous: &ConnectionConfig, config: &ConnectionConfig) -> bool {
+    fn settings(config: &ConnectionConfig) -> impl PartialEq + '_ {
+        (
+            (
+                &config.host,
+                config.port,
+                &config.username,
+                &config.password,
+                config.database,
+                config.mode,
+                &config.socket_path,
+                &config.sentinel,
+            ),
+            (
+                config.tls_enabled,
+                &config.tls_ca_cert_path,
+                &config.tls_client_cert_path,
+                &config.tls_client_key_path,
+                config.tls_skip_verify,
+                &config.tls_server_name,
+                &config.ssh,
+                &config.proxy,
+            ),
+            (
+                config.resp3,
+                config.connect_timeout_secs,
+                config.response_timeout_secs,
+                config.keepalive_secs,
+                &config.client_name,
+                // Replica reads are chosen when a cluster is connected.
+                config.read_only,
+            ),
+        )
+    }
+
+    settings(previous) != settings(config)
+}
+
+/// Keeps a live connection open under its new name. The sentinel watcher
+/// looks the connection up by name, so it is restarted.
+fn rename_live_connection(
+    app_handle: &tauri::AppHandle,
+    old: &str,
+    new: &str,
+) -> Result<(), String> {
+    let state = app_handle.state::<Mutex<AppState>>();
+    let mut state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if !state.rename_connection(old, new) {
+        return Ok(());
+    }
+
+    if let Some(live) = state.connections.get_mut(new) {
+        if live.sentinel_watcher.is_some() {
+            live.sentinel_watcher = Some(SentinelWatcher::spawn(
+                app_handle.clone(),
+                live.config.clone(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Gives a live connection the saved settings that apply without
+/// reconnecting, such as the key delimiters or the environment.
+fn update_live_config(
+    app_handle: &tauri::AppHandle,
+    config: ConnectionConfig,
+) -> Result<(), String> {
+    let state = app_handle.state::<Mutex<AppState>>();
+    let mut state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if let Some(live) = state.connections.get_mut(&config.name) {
+        // The fields holding secrets are unchanged, and only the live copy has
+        // them decrypted.
+        live.config = ConnectionConfig {
+            password: std::mem::take(&mut live.config.password),
+            ssh: live.config.ssh.take(),
+            sentinel: live.config.sentinel.take(),
+            proxy: live.config.proxy.take(),
+            ..config
+        };
+    }
+
+    Ok(())
+}
+
+fn close_connection(app_handle: &tauri::AppHandle, name: &str) -> Result<(), String> {
+    let state = app_handle.state::<Mutex<AppState>>();
+    let mut state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    if state.remove_connection(name) {
+        info!("Closed live connection '{}' after its configuration changed", name);
+    }
 
     Ok(())
 }
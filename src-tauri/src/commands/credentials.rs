@@ -0,0 +1,86 @@
+use log::{error, info};
+use tadis_database::{
+    crypto::Credentials,
+    queries::{encrypt_stored_secrets, get_stored_connection_configurations, update_connection_secrets},
+};
+use tauri::{async_runtime::Mutex as AsyncMutex, Manager, State};
+
+use crate::models::connection::CredentialsStatus;
+
+#[tauri::command]
+pub async fn get_credentials_status(
+    credentials: State<'_, AsyncMutex<Credentials>>,
+) -> Result<CredentialsStatus, String> {
+    let credentials = credentials.lock().await;
+
+    Ok(CredentialsStatus {
+        master_password: credentials.has_master_password(),
+        locked: credentials.is_locked(),
+        error: credentials.load_error().map(str::to_string),
+    })
+}
+
+#[tauri::command]
+pub async fn unlock_credentials(
+    app_handle: tauri::AppHandle,
+    master_password: String,
+) -> Result<(), String> {
+    app_handle
+        .state::<AsyncMutex<Credentials>>()
+        .lock()
+        .await
+        .unlock(&master_password)?;
+
+    encrypt_stored_secrets(&app_handle)
+        .await
+        .map_err(|e| format!("Failed to encrypt stored secrets: {}", e))?;
+
+    Ok(())
+}
+
+/// Re-encrypts every stored secret under a key derived from `master_password`,
+/// or under a new key file when it is `None`.
+#[tauri::command]
+pub async fn set_master_password(
+    app_handle: tauri::AppHandle,
+    master_password: Option<String>,
+) -> Result<(), String> {
+    let master_password = master_password.filter(|password| !password.is_empty());
+
+    // Held until the new key is in use. Saving a connection waits for it, so
+    // nothing is encrypted under the old key once it is replaced, and no row
+    // is added after the secrets were loaded.
+    let state = app_handle.state::<AsyncMutex<Credentials>>();
+    let mut credentials = state.lock().await;
+
+    let mut configs = get_stored_connection_configurations(&app_handle)
+        .await
+        .map_err(|e| format!("Failed to load connection configurations: {}", e))?;
+    let next = credentials.with_master_password(master_password.as_deref())?;
+    for config in configs.iter_mut() {
+        credentials.decrypt_config(config)?;
+        next.encrypt_config(config)?;
+    }
+
+    // The new key goes to disk first, so secrets are never stored under a key
+    // that was lost. They are re-encrypted in one transaction, and the old key
+    // is put back if that fails, as they are then still encrypted under it.
+    next.save()?;
+    if let Err(e) = update_connection_secrets(&app_handle, &configs).await {
+        if let Err(restore_error) = credentials.save() {
+            error!(
+                "Failed to restore the previous credential key: {}",
+                restore_error
+            );
+        }
+        return Err(format!("Failed to save re-encrypted secrets: {}", e));
+    }
+
+    *credentials = next;
+    info!(
+        "Credentials are now protected by {}",
+        if master_password.is_some() { "a master password" } else { "the local key file" }
+    );
+
+    Ok(())
+}
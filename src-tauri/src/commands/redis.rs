@@ -1,104 +1,622 @@
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-use redis::Commands;
-use tauri::{command, State};
+use log::warn;
+use redis::cluster_routing::get_slot;
+use redis::{AsyncCommands, FromRedisValue, RedisError, RedisResult};
+use tauri::{command, AppHandle, Emitter, State, Webview};
 
 use crate::models::redis::{
     ListDirection, RedisItem, RedisItemValue, RedisServerStatistics, RedisTreeItem, RedisClientInfo,
+    ScanCursor, ScanPage, ScanProgressEvent, TreeItemKind,
 };
+use crate::client::RedisConnection;
+use crate::key_cache::{KeyCache, KeyDetails, KeyMeta};
+use crate::health;
 use crate::AppState;
 
 const LIST_DELETED_VALUE_PLACEHOLDER: &str = "__TADIS_DELETED_VALUE_PLACEHOLDER__";
-
+pub const SCAN_PROGRESS_EVENT: &str = "scan-progress";
+/// `COUNT` hint sent with every `SCAN`, unless the caller picks another.
+const DEFAULT_SCAN_COUNT: u32 = 1000;
+/// Keys returned per page of `scan_keys`, unless the caller picks another.
+const DEFAULT_SCAN_PAGE_SIZE: usize = 10_000;
+/// Keys scanned per page of `get_prefix_children`, which also caps the keys it
+/// returns.
+const PREFIX_PAGE_SIZE: usize = 10_000;
+/// Keys looked up per pipeline when resolving key types.
+const KEY_META_BATCH_SIZE: usize = 500;
+
+/// Every key, as one tree. Runs the paged scan of `scan_keys` to the end, on
+/// every master of a cluster, so prefer `scan_keys` on large keyspaces.
 #[command]
-pub fn get_all_keys_as_tree(
+pub async fn get_all_keys_as_tree(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
 ) -> Result<Vec<RedisTreeItem>, String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
-
-    let keys: Vec<String> = client
-        .keys("*")
-        .map_err(|e| format!("Failed to get keys: {}", e))?;
-
-    let mut result = convert_keys_to_tree(client, keys);
-    result.sort_by(|a, b| match (a.children.is_some(), b.children.is_some()) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.label.cmp(&b.label),
-    });
-
-    Ok(result)
+    let selected = selected_client_with_name(&state, &webview, connection_id.as_deref())?;
+    let tree = key_tree(&state, &selected.0)?;
+    with_retry(&state, selected, |mut client| {
+        let tree = tree.clone();
+        async move { get_keys_as_tree(&mut client, &tree, "*").await }
+    })
+    .await
 }
 
+/// Every key matching `search_term`, as one tree, see `get_all_keys_as_tree`.
 #[command]
-pub fn search_keys_as_tree(
+pub async fn search_keys_as_tree(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     search_term: String,
 ) -> Result<Vec<RedisTreeItem>, String> {
-    let mut state = state
+    let pattern = search_pattern(Some(&search_term));
+    let selected = selected_client_with_name(&state, &webview, connection_id.as_deref())?;
+    let tree = key_tree(&state, &selected.0)?;
+    with_retry(&state, selected, |mut client| {
+        let pattern = pattern.clone();
+        let tree = tree.clone();
+        async move { get_keys_as_tree(&mut client, &tree, &pattern).await }
+    })
+    .await
+}
+
+/// Scans one page of keys matching `search_term`, or all keys without one,
+/// starting at `cursor` or from the beginning. Every `SCAN` round trip is
+/// reported as a scan progress event tagged with `scan_id`, which
+/// `cancel_scan` takes to stop the scan early, so it must not be used by another
+/// running scan. With `details`, tree items also carry their TTL and memory
+/// usage.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn scan_keys(
+    app_handle: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
+    scan_id: String,
+    search_term: Option<String>,
+    count: Option<u32>,
+    page_size: Option<usize>,
+    cursor: Option<ScanCursor>,
+    details: Option<bool>,
+) -> Result<ScanPage, String> {
+    let (name, mut client) = selected_client_with_name(&state, &webview, connection_id.as_deref())?;
+    let tree = key_tree(&state, &name)?;
+    let running = RunningScan::start(&state, scan_id)?;
+
+    let scan = KeyScan {
+        pattern: search_pattern(search_term.as_deref()),
+        count: count
+            .filter(|count| *count > 0)
+            .unwrap_or(DEFAULT_SCAN_COUNT),
+        tree,
+        details: details.unwrap_or_default(),
+    };
+    let page_size = page_size
+        .filter(|size| *size > 0)
+        .unwrap_or(DEFAULT_SCAN_PAGE_SIZE);
+    let result = scan
+        .page(
+            &mut client,
+            cursor,
+            page_size,
+            &running.cancelled,
+            running.progress(&app_handle),
+        )
+        .await;
+
+    running.finish(&state);
+    result.map_err(String::from)
+}
+
+/// One page of the direct children of the folder `prefix`, such as `user:`,
+/// or of the root when empty: the keys right under it, and a folder with its
+/// key count for every namespace below. Only keys under `prefix` are scanned,
+/// so folders can be expanded one at a time.
+///
+/// Pages work like those of `scan_keys`. Folder counts only cover the keys of
+/// their page, so the counts of all pages add up to the full ones.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_prefix_children(
+    app_handle: AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
+    scan_id: String,
+    prefix: String,
+    cursor: Option<ScanCursor>,
+) -> Result<ScanPage, String> {
+    let (name, mut client) = selected_client_with_name(&state, &webview, connection_id.as_deref())?;
+    let tree = key_tree(&state, &name)?;
+    let running = RunningScan::start(&state, scan_id)?;
+
+    let result = list_prefix(
+        &mut client,
+        &tree,
+        &prefix,
+        cursor,
+        &running.cancelled,
+        running.progress(&app_handle),
+    )
+    .await;
+
+    running.finish(&state);
+    result.map_err(String::from)
+}
+
+/// Stops the scan started with `scan_id` after its current round trip. The
+/// keys found so far are still returned, with a cursor to resume from.
+#[command]
+pub fn cancel_scan(state: State<'_, Mutex<AppState>>, scan_id: String) -> Result<(), String> {
+    let state = state
         .lock()
         .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
-
-    let keys: Vec<String> = client
-        .keys(&format!("*{}*", search_term))
-        .map_err(|e| format!("Failed to get keys: {}", e))?;
-
-    let mut result = convert_keys_to_tree(client, keys);
-    result.sort_by(|a, b| match (a.children.is_some(), b.children.is_some()) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.label.cmp(&b.label),
-    });
+    if let Some(cancelled) = state.scans.get(&scan_id) {
+        cancelled.store(true, Ordering::Relaxed);
+    }
 
-    Ok(result)
+    Ok(())
 }
 
-#[command]
-pub fn get_key_detail(state: State<'_, Mutex<AppState>>, key: String) -> Result<RedisItem, String> {
-    let mut state = state
+/// A scan registered under its id, so `cancel_scan` can stop it.
+struct RunningScan {
+    id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RunningScan {
+    /// Registers the scan `id`, refusing one that is already running.
+    fn start(state: &Mutex<AppState>, id: String) -> Result<Self, String> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        match state
+            .lock()
+            .map_err(|e| format!("Failed to lock state: {}", e))?
+            .scans
+            .entry(id.clone())
+        {
+            Entry::Occupied(_) => return Err(format!("Scan '{}' is already running", id)),
+            Entry::Vacant(entry) => entry.insert(cancelled.clone()),
+        };
+
+        Ok(Self { id, cancelled })
+    }
+
+    /// Reports every `SCAN` round trip as a scan progress event.
+    fn progress<'a>(
+        &'a self,
+        app_handle: &'a AppHandle,
+    ) -> impl FnMut(Vec<String>, usize, Option<ScanCursor>) + 'a {
+        |keys, found, cursor| {
+            let event = ScanProgressEvent {
+                scan_id: self.id.clone(),
+                keys,
+                found,
+                cursor,
+            };
+            if let Err(e) = app_handle.emit(SCAN_PROGRESS_EVENT, event) {
+                warn!("Failed to emit '{}': {}", SCAN_PROGRESS_EVENT, e);
+            }
+        }
+    }
+
+    fn finish(self, state: &Mutex<AppState>) {
+        if let Ok(mut state) = state.lock() {
+            state.scans.remove(&self.id);
+        }
+    }
+}
+
+fn search_pattern(search_term: Option<&str>) -> String {
+    match search_term.filter(|term| !term.is_empty()) {
+        Some(term) => format!("*{}*", term),
+        None => "*".to_string(),
+    }
+}
+
+/// The client for a command that writes a key, and the key cache of its
+/// connection. Read-only connections are refused here, whatever the UI lets
+/// through. The command invalidates its key once the write succeeded, as the
+/// write may change what is cached. Any earlier and a scan running alongside
+/// could cache the old type again.
+fn writable_client(
+    state: &Mutex<AppState>,
+    webview: &Webview,
+    connection_id: Option<&str>,
+) -> Result<(RedisConnection, KeyCache), String> {
+    let state = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?;
+    let (name, client) = state.resolve_client(connection_id, webview.label())?;
+    if state.is_read_only(&name) {
+        return Err(format!("Connection '{}' is read-only", name));
+    }
+    let cache = state.key_cache(&name).unwrap_or_default();
+
+    Ok((client, cache))
+}
+
+/// How the keys of a connection are laid out as a tree.
+#[derive(Clone)]
+struct KeyTree {
+    cache: KeyCache,
+    /// Separators between folders, none for a flat list.
+    delimiters: Vec<String>,
+}
+
+impl KeyTree {
+    /// Splits `key` at its first delimiter into the folder name, the
+    /// delimiter and the rest. `None` when `key` has no delimiter left.
+    fn split<'a>(&self, key: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
+        let (index, len) = self
+            .delimiters
+            .iter()
+            .filter_map(|delimiter| Some((key.find(delimiter.as_str())?, delimiter.len())))
+            // The longest delimiter wins where several start at the same place.
+            .min_by_key(|(index, len)| (*index, std::cmp::Reverse(*len)))?;
+        let end = index + len;
+        Some((&key[..index], &key[index..end], &key[end..]))
+    }
+}
+
+/// The key cache and delimiters of the named connection.
+fn key_tree(state: &Mutex<AppState>, name: &str) -> Result<KeyTree, String> {
+    let state = state
         .lock()
         .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let live = state
+        .connections
+        .get(name)
+        .ok_or(format!("Connection '{}' is not connected.", name))?;
+
+    Ok(KeyTree {
+        cache: live.key_cache.clone(),
+        delimiters: live.config.key_delimiters(),
+    })
+}
+
+/// A handle to the connection named by `connection_id`, or else the one the
+/// calling webview selected. The state lock is released before returning.
+fn selected_client_with_name(
+    state: &Mutex<AppState>,
+    webview: &Webview,
+    connection_id: Option<&str>,
+) -> Result<(String, RedisConnection), String> {
+    state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .resolve_client(connection_id, webview.label())
+}
+
+/// Runs `query` on the `selected` client. If the connection failed under it,
+/// reconnects and retries once, so `query` must be safe to run twice. Error
+/// replies from the server are returned as they are.
+async fn with_retry<T, F, Fut>(
+    state: &Mutex<AppState>,
+    selected: (String, RedisConnection),
+    mut query: F,
+) -> Result<T, String>
+where
+    F: FnMut(RedisConnection) -> Fut,
+    Fut: Future<Output = Result<T, CommandError>>,
+{
+    let (selected, client) = selected;
+
+    match query(client).await {
+        Err(e) if e.connection_failed => {
+            warn!(
+                "Connection '{}' dropped, reconnecting: {}",
+                selected, e.message
+            );
+            let client = health::reconnect(state, &selected).await?;
+            query(client).await.map_err(String::from)
+        }
+        result => result.map_err(String::from),
+    }
+}
+
+/// The error of a query run through `with_retry`, which tells a failed
+/// connection apart from an error reply.
+struct CommandError {
+    message: String,
+    connection_failed: bool,
+}
+
+impl CommandError {
+    fn redis(context: &str, e: RedisError) -> Self {
+        Self {
+            message: format!("{}: {}", context, e),
+            connection_failed: e.is_io_error(),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            connection_failed: false,
+        }
+    }
+}
+
+impl From<CommandError> for String {
+    fn from(e: CommandError) -> Self {
+        e.message
+    }
+}
+
+/// Every key matching `pattern`, scanned as a single page that is never
+/// cut short.
+async fn get_keys_as_tree(
+    client: &mut RedisConnection,
+    tree: &KeyTree,
+    pattern: &str,
+) -> Result<Vec<RedisTreeItem>, CommandError> {
+    let scan = KeyScan {
+        pattern: pattern.to_string(),
+        count: DEFAULT_SCAN_COUNT,
+        tree: tree.clone(),
+        details: false,
+    };
+    let page = scan
+        .page(
+            client,
+            None,
+            usize::MAX,
+            &AtomicBool::new(false),
+            |_, _, _| {},
+        )
+        .await?;
+
+    Ok(page.items)
+}
+
+/// A `SCAN ... MATCH pattern COUNT count` over the whole keyspace, one master
+/// after another on a cluster.
+struct KeyScan {
+    pattern: String,
+    count: u32,
+    tree: KeyTree,
+    /// Whether to look up TTL and memory usage along with the key types.
+    details: bool,
+}
+
+/// Keys found by one run of a `KeyScan`.
+struct ScannedKeys {
+    keys: Vec<String>,
+    /// Where to resume, `None` once the keyspace is exhausted.
+    cursor: Option<ScanCursor>,
+    slot_owners: Option<Vec<SlotOwner>>,
+}
+
+impl KeyScan {
+    /// Scans one page and builds the tree of the keys found, see `keys`.
+    async fn page(
+        &self,
+        client: &mut RedisConnection,
+        cursor: Option<ScanCursor>,
+        page_size: usize,
+        cancelled: &AtomicBool,
+        progress: impl FnMut(Vec<String>, usize, Option<ScanCursor>),
+    ) -> Result<ScanPage, CommandError> {
+        let scanned = self
+            .keys(client, cursor, page_size, cancelled, progress)
+            .await?;
+        let mut items = convert_keys_to_tree(
+            client,
+            &self.tree,
+            scanned.keys,
+            scanned.slot_owners.as_deref(),
+            self.details,
+        )
+        .await;
+        sort_tree(&mut items);
+
+        Ok(ScanPage {
+            items,
+            cursor: scanned.cursor,
+            cancelled: cancelled.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Scans from `cursor` until at least `page_size` keys matched, the
+    /// keyspace is exhausted or `cancelled` is set, calling `progress` with
+    /// the keys of every round trip.
+    async fn keys(
+        &self,
+        client: &mut RedisConnection,
+        cursor: Option<ScanCursor>,
+        page_size: usize,
+        cancelled: &AtomicBool,
+        mut progress: impl FnMut(Vec<String>, usize, Option<ScanCursor>),
+    ) -> Result<ScannedKeys, CommandError> {
+        let slot_owners = get_slot_owners(client).await?;
+        let mut cursor = match cursor {
+            Some(cursor) => cursor,
+            None => ScanCursor {
+                nodes: masters(slot_owners.as_deref()),
+                cursor: 0,
+            },
+        };
+
+        let mut keys: Vec<String> = Vec::new();
+        let mut done = false;
+        while !done && keys.len() < page_size && !cancelled.load(Ordering::Relaxed) {
+            let (next, batch): (u64, Vec<String>) = client
+                .query_node(
+                    redis::cmd("SCAN")
+                        .arg(cursor.cursor)
+                        .arg("MATCH")
+                        .arg(&self.pattern)
+                        .arg("COUNT")
+                        .arg(self.count),
+                    cursor.nodes.first().map(String::as_str),
+                )
+                .await
+                .map_err(|e| CommandError::redis("Failed to scan keys", e))?;
+
+            cursor.cursor = next;
+            if next == 0 {
+                // This server is done, move on to the next master if any.
+                if !cursor.nodes.is_empty() {
+                    cursor.nodes.remove(0);
+                }
+                done = cursor.nodes.is_empty();
+            }
+
+            keys.extend(batch.iter().cloned());
+            progress(batch, keys.len(), (!done).then(|| cursor.clone()));
+        }
+        // SCAN may return a key more than once.
+        keys.sort_unstable();
+        keys.dedup();
+
+        Ok(ScannedKeys {
+            keys,
+            cursor: (!done).then_some(cursor),
+            slot_owners,
+        })
+    }
+}
+
+async fn list_prefix(
+    client: &mut RedisConnection,
+    tree: &KeyTree,
+    prefix: &str,
+    cursor: Option<ScanCursor>,
+    cancelled: &AtomicBool,
+    progress: impl FnMut(Vec<String>, usize, Option<ScanCursor>),
+) -> Result<ScanPage, CommandError> {
+    let scan = KeyScan {
+        pattern: format!("{}*", escape_pattern(prefix)),
+        count: DEFAULT_SCAN_COUNT,
+        tree: tree.clone(),
+        details: false,
+    };
+    let scanned = scan
+        .keys(client, cursor, PREFIX_PAGE_SIZE, cancelled, progress)
+        .await?;
+
+    let mut keys: Vec<String> = Vec::new();
+    // Keyed by the folder's own prefix, as folders may share a name across
+    // delimiters.
+    let mut folders: HashMap<String, (String, usize)> = HashMap::new();
+    for key in scanned.keys {
+        match tree.split(&key[prefix.len()..]) {
+            Some((folder, delimiter, _)) => {
+                let folder_prefix = format!("{}{}{}", prefix, folder, delimiter);
+                folders
+                    .entry(folder_prefix)
+                    .or_insert_with(|| (folder.to_string(), 0))
+                    .1 += 1;
+            }
+            None => keys.push(key),
+        }
+    }
+
+    let slot_owners = scanned.slot_owners.as_deref();
+    let metas = get_key_metas(client, &tree.cache, &keys, slot_owners, false).await;
+    let mut items: Vec<RedisTreeItem> = folders
+        .into_iter()
+        .map(|(folder_prefix, (folder, key_count))| RedisTreeItem {
+            key_count: Some(key_count),
+            ..folder_item(&folder_prefix, &folder, None)
+        })
+        .collect();
+    items.extend(
+        keys.iter()
+            .map(|key| key_item(key, &key[prefix.len()..], metas.get(key), slot_owners)),
+    );
+    sort_tree(&mut items);
+
+    Ok(ScanPage {
+        items,
+        cursor: scanned.cursor,
+        cancelled: cancelled.load(Ordering::Relaxed),
+    })
+}
+
+/// Escapes the glob characters of `text`, for a `MATCH` pattern matching it
+/// literally.
+fn escape_pattern(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Distinct masters in slot order, as the nodes a cluster scan visits.
+fn masters(slot_owners: Option<&[SlotOwner]>) -> Vec<String> {
+    let mut nodes: Vec<String> = Vec::new();
+    for owner in slot_owners.unwrap_or_default() {
+        if !nodes.contains(&owner.node) {
+            nodes.push(owner.node.clone());
+        }
+    }
+    nodes
+}
+
+/// Folders first, then keys, each by label.
+fn sort_tree(items: &mut [RedisTreeItem]) {
+    items.sort_by(|a, b| match (a.kind, b.kind) {
+        (TreeItemKind::Folder, TreeItemKind::Key) => std::cmp::Ordering::Less,
+        (TreeItemKind::Key, TreeItemKind::Folder) => std::cmp::Ordering::Greater,
+        _ => a.label.cmp(&b.label),
+    });
+}
+
+#[command]
+pub async fn get_key_detail(
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
+    key: String,
+) -> Result<RedisItem, String> {
+    let selected = selected_client_with_name(&state, &webview, connection_id.as_deref())?;
+    with_retry(&state, selected, |mut client| {
+        let key = key.clone();
+        async move { read_key_detail(&mut client, key).await }
+    })
+    .await
+}
 
-    println!("key: {:?}", key);
+async fn read_key_detail(
+    client: &mut RedisConnection,
+    key: String,
+) -> Result<RedisItem, CommandError> {
     let key_type: String = client
         .key_type(&key)
-        .map_err(|e| format!("Failed to get key type: {}", e))?;
+        .await
+        .map_err(|e| CommandError::redis("Failed to get key type", e))?;
 
-    let (size, ttl): (i64, i64) = redis::pipe()
+    // TTL goes first so a cluster routes the pipeline by its key argument.
+    let (ttl, size): (i64, i64) = redis::pipe()
         .atomic()
+        .cmd("TTL")
+        .arg(&key)
         .cmd("MEMORY")
         .arg("USAGE")
         .arg(&key)
-        .cmd("TTL")
-        .arg(&key)
-        .query(client)
-        .map_err(|e| format!("Failed to query Redis: {}", e))?;
-
-    println!("key_type: {:?}, size: {:?}, ttl: {:?}", key_type, size, ttl);
+        .query_async(client)
+        .await
+        .map_err(|e| CommandError::redis("Failed to query Redis", e))?;
 
     match key_type.as_str() {
         "string" => {
             let value = RedisItem {
                 redis_key: key.clone(),
-                value: RedisItemValue::StringValue(get_string(client, key)?),
+                value: RedisItemValue::StringValue(get_string(client, key).await?),
                 ttl: ttl,
                 size: size,
             };
@@ -107,7 +625,7 @@ pub fn get_key_detail(state: State<'_, Mutex<AppState>>, key: String) -> Result<
         "list" => {
             let value = RedisItem {
                 redis_key: key.clone(),
-                value: RedisItemValue::ListValue(get_list(client, key)?),
+                value: RedisItemValue::ListValue(get_list(client, key).await?),
                 ttl: ttl,
                 size: size,
             };
@@ -116,7 +634,7 @@ pub fn get_key_detail(state: State<'_, Mutex<AppState>>, key: String) -> Result<
         "set" => {
             let value = RedisItem {
                 redis_key: key.clone(),
-                value: RedisItemValue::SetValue(get_set(client, key)?),
+                value: RedisItemValue::SetValue(get_set(client, key).await?),
                 ttl: ttl,
                 size: size,
             };
@@ -125,7 +643,7 @@ pub fn get_key_detail(state: State<'_, Mutex<AppState>>, key: String) -> Result<
         "hash" => {
             let value = RedisItem {
                 redis_key: key.clone(),
-                value: RedisItemValue::HashValue(get_hash(client, key)?),
+                value: RedisItemValue::HashValue(get_hash(client, key).await?),
                 ttl: ttl,
                 size: size,
             };
@@ -134,57 +652,51 @@ pub fn get_key_detail(state: State<'_, Mutex<AppState>>, key: String) -> Result<
         "stream" => {
             let value = RedisItem {
                 redis_key: key.clone(),
-                value: RedisItemValue::StreamValue(get_stream(client, key)?),
+                value: RedisItemValue::StreamValue(get_stream(client, key).await?),
                 ttl,
                 size,
             };
-            println!("stream value: {:?}", value);
             Ok(value)
         }
         "zset" => {
             let value = RedisItem {
                 redis_key: key.clone(),
-                value: RedisItemValue::ZSetValue(get_zset(client, key)?),
+                value: RedisItemValue::ZSetValue(get_zset(client, key).await?),
                 ttl,
                 size,
             };
             Ok(value)
         }
-        _ => Err(format!("Unsupported key type: {}", key_type)),
+        _ => Err(format!("Unsupported key type: {}", key_type).into()),
     }
 }
 #[command]
-pub fn delete_key(state: State<'_, Mutex<AppState>>, key: String) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+pub async fn delete_key(
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
+    key: String,
+) -> Result<(), String> {
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .del(&key)
+        .await
         .map_err(|e| format!("Failed to delete key: {}", e))?;
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn add_list(
+pub async fn add_list(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     items: Vec<String>,
     ttl: Option<i64>,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     let mut pipe = redis::pipe();
     pipe.rpush(&key, items);
@@ -194,26 +706,23 @@ pub fn add_list(
         }
     }
 
-    pipe.query(client)
+    pipe.exec_async(&mut client)
+        .await
         .map_err(|e| format!("Failed to add list: {}", e))?;
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn save_string(
+pub async fn save_string(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     value: String,
     ttl: Option<i64>,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     let mut pipe = redis::pipe();
     pipe.set(&key, value);
@@ -223,108 +732,98 @@ pub fn save_string(
         }
     }
 
-    pipe.query(client)
+    pipe.exec_async(&mut client)
+        .await
         .map_err(|e| format!("Failed to save string: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn list_add_items(
+pub async fn list_add_items(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     items: Vec<String>,
     direction: ListDirection,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     match direction {
         ListDirection::Start => {
-            client
+            let _: () = client
                 .lpush(&key, items)
+                .await
                 .map_err(|e| format!("Failed to add items: {}", e))?;
         }
         ListDirection::End => {
-            client
+            let _: () = client
                 .rpush(&key, items)
+                .await
                 .map_err(|e| format!("Failed to add items: {}", e))?;
         }
     }
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn list_update_value(
+pub async fn list_update_value(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     index: i64,
     value: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .lset(&key, index as isize, &value)
+        .await
         .map_err(|e| format!("Failed to update value: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn list_delete_value(
+pub async fn list_delete_value(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     index: i64,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .lset(&key, index as isize, LIST_DELETED_VALUE_PLACEHOLDER)
+        .await
         .map_err(|e| format!("Failed to delete value: {}", e))?;
 
-    client
+    let _: () = client
         .lrem(&key, 0, &LIST_DELETED_VALUE_PLACEHOLDER)
+        .await
         .map_err(|e| format!("Failed to delete value: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn set_add_items(
+pub async fn set_add_items(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     items: Vec<String>,
     ttl: Option<i64>,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     let mut pipe = redis::pipe();
     pipe.sadd(&key, items);
@@ -333,76 +832,68 @@ pub fn set_add_items(
             pipe.expire(&key, ttl);
         }
     }
-    pipe.query(client)
+    pipe.exec_async(&mut client)
+        .await
         .map_err(|e| format!("Failed to add items: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn set_update_value(
+pub async fn set_update_value(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     value: String,
     new_value: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .srem(&key, &value)
+        .await
         .map_err(|e| format!("Failed to update value: {}", e))?;
 
-    client
+    let _: () = client
         .sadd(&key, &new_value)
+        .await
         .map_err(|e| format!("Failed to update value: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn set_delete_value(
+pub async fn set_delete_value(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     value: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .srem(&key, &value)
+        .await
         .map_err(|e| format!("Failed to delete value: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn hash_add_items(
+pub async fn hash_add_items(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     items: Vec<(String, String)>,
     ttl: Option<i64>,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     let mut pipe = redis::pipe();
     pipe.hset_multiple(&key, &items);
@@ -411,100 +902,89 @@ pub fn hash_add_items(
             pipe.expire(&key, ttl);
         }
     }
-    pipe.query(client)
+    pipe.exec_async(&mut client)
+        .await
         .map_err(|e| format!("Failed to add items: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn hash_delete_field(
+pub async fn hash_delete_field(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     field: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .hdel(&key, &field)
+        .await
         .map_err(|e| format!("Failed to delete field: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn hash_update_value(
+pub async fn hash_update_value(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     field: String,
     value: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .hset(&key, &field, &value)
+        .await
         .map_err(|e| format!("Failed to update field: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn hash_update_field(
+pub async fn hash_update_field(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     old_field: String,
     new_field: String,
     value: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .hdel(&key, &old_field)
+        .await
         .map_err(|e| format!("Failed to delete field: {}", e))?;
 
-    client
+    let _: () = client
         .hset(&key, &new_field, &value)
+        .await
         .map_err(|e| format!("Failed to update key: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn add_zset_items(
+pub async fn add_zset_items(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     items: Vec<(f64, String)>,
     ttl: Option<i64>,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     let mut pipe = redis::pipe();
     pipe.zadd_multiple(&key, &items);
@@ -513,135 +993,120 @@ pub fn add_zset_items(
             pipe.expire(&key, ttl);
         }
     }
-    pipe.query(client)
+    pipe.exec_async(&mut client)
+        .await
         .map_err(|e| format!("Failed to add items: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn zset_add_items(
+pub async fn zset_add_items(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     items: Vec<(f64, String)>,
     replace: bool,
 ) -> Result<(), String> {
-    println!("zset_add_items: {:?}", items);
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     if replace {
-        println!("replace");
-        client
+        let _: () = client
             .zadd_multiple(&key, &items)
+            .await
             .map_err(|e| format!("Failed to add items: {}", e))?;
     } else {
         redis::cmd("ZADD")
-            .arg(key)
+            .arg(&key)
             .arg("NX")
             .arg(items)
-            .exec(client)
+            .exec_async(&mut client)
+            .await
             .map_err(|e| format!("Failed to add items: {}", e))?;
     }
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn zset_delete_value(
+pub async fn zset_delete_value(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     value: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .zrem(&key, &value)
+        .await
         .map_err(|e| format!("Failed to delete value: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn zset_update_score(
+pub async fn zset_update_score(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     member: String,
     score: f64,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .zadd(&key, &member, score)
+        .await
         .map_err(|e| format!("Failed to update score: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn zset_update_member(
+pub async fn zset_update_member(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     old_member: String,
     new_member: String,
     score: f64,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
-    client
+    let _: () = client
         .zrem(&key, &old_member)
+        .await
         .map_err(|e| format!("Failed to delete member: {}", e))?;
 
-    client
+    let _: () = client
         .zadd(&key, &new_member, score)
+        .await
         .map_err(|e| format!("Failed to add member: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn stream_add_items(
+pub async fn stream_add_items(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     id: Option<String>,
     items: Vec<(String, String)>,
     ttl: Option<i64>,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     let id = id.unwrap_or("*".to_string());
     let mut pipe = redis::pipe();
@@ -651,92 +1116,90 @@ pub fn stream_add_items(
             pipe.expire(&key, ttl);
         }
     }
-    pipe.query(client)
+    pipe.exec_async(&mut client)
+        .await
         .map_err(|e| format!("Failed to add items: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn stream_delete_value(
+pub async fn stream_delete_value(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
     key: String,
     id: String,
 ) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
-    client
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
+    let _: () = client
         .xdel(&key, &[&id])
+        .await
         .map_err(|e| format!("Failed to delete value: {}", e))?;
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn update_ttl(state: State<'_, Mutex<AppState>>, key: String, ttl: i64) -> Result<(), String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
+pub async fn update_ttl(
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
+    key: String,
+    ttl: i64,
+) -> Result<(), String> {
+    let (mut client, cache) = writable_client(&state, &webview, connection_id.as_deref())?;
 
     if ttl == -1 {
-        client
+        let _: () = client
             .persist(&key)
+            .await
             .map_err(|e| format!("Failed to update ttl: {}", e))?;
     } else {
-        client
+        let _: () = client
             .expire(&key, ttl)
+            .await
             .map_err(|e| format!("Failed to update ttl: {}", e))?;
     }
 
+    cache.invalidate(&key);
     Ok(())
 }
 
 #[command]
-pub fn get_server_statistics(
+pub async fn get_server_statistics(
     state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
 ) -> Result<RedisServerStatistics, String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
-
-    let info: String = redis::cmd("INFO")
-        .query(client)
-        .map_err(|e| format!("Failed to get server info: {}", e))?;
+    let selected = selected_client_with_name(&state, &webview, connection_id.as_deref())?;
+    let info: String = with_retry(&state, selected, |mut client| async move {
+        client
+            .query_one_node(&redis::cmd("INFO"))
+            .await
+            .map_err(|e| CommandError::redis("Failed to get server info", e))
+    })
+    .await?;
 
     Ok(RedisServerStatistics::from(info))
 }
 
 #[command]
-pub fn get_client_list(state: State<'_, Mutex<AppState>>) -> Result<Vec<RedisClientInfo>, String> {
-    let mut state = state
-        .lock()
-        .map_err(|e| format!("Failed to lock state: {}", e))?;
-    let selected = state.selected_client.clone();
-    let client = state
-        .connected_clients
-        .get_mut(&selected)
-        .ok_or(format!("No client selected"))?;
-
-    let info: String = redis::cmd("CLIENT")
-        .arg("LIST")
-        .query(client)
-        .map_err(|e| format!("Failed to get client list: {}", e))?;
+pub async fn get_client_list(
+    state: State<'_, Mutex<AppState>>,
+    webview: Webview,
+    connection_id: Option<String>,
+) -> Result<Vec<RedisClientInfo>, String> {
+    let selected = selected_client_with_name(&state, &webview, connection_id.as_deref())?;
+    let info: String = with_retry(&state, selected, |mut client| async move {
+        client
+            .query_one_node(redis::cmd("CLIENT").arg("LIST"))
+            .await
+            .map_err(|e| CommandError::redis("Failed to get client list", e))
+    })
+    .await?;
 
     let clients = info
         .lines()
@@ -768,114 +1231,286 @@ pub fn get_client_list(state: State<'_, Mutex<AppState>>) -> Result<Vec<RedisCli
     Ok(clients)
 }
 
-fn convert_keys_to_tree(client: &mut redis::Connection, keys: Vec<String>) -> Vec<RedisTreeItem> {
-    let mut root_items: Vec<RedisTreeItem> = Vec::new();
+/// Slots `start..=end` are served by the master at `node`.
+struct SlotOwner {
+    start: u16,
+    end: u16,
+    node: String,
+}
 
-    for key in keys {
-        let parts: Vec<&str> = key.split(':').collect();
-        let item_type = get_key_type(client, key.clone()).unwrap_or_default();
-        let mut current_items = &mut root_items;
-        let mut current_path = String::new();
+/// Reads the slot map of a cluster, or `None` on a single server.
+async fn get_slot_owners(
+    client: &mut RedisConnection,
+) -> Result<Option<Vec<SlotOwner>>, CommandError> {
+    if !client.is_cluster() {
+        return Ok(None);
+    }
+
+    // Each entry is [start, end, [master ip, master port, ...], replicas...].
+    let ranges: Vec<Vec<redis::Value>> = client
+        .query_one_node(redis::cmd("CLUSTER").arg("SLOTS"))
+        .await
+        .map_err(|e| CommandError::redis("Failed to get cluster slots", e))?;
+
+    let owners = ranges
+        .iter()
+        .filter_map(|range| {
+            let master: Vec<redis::Value> = redis::from_redis_value(range.get(2)?).ok()?;
+            let ip: String = redis::from_redis_value(master.first()?).ok()?;
+            let port: u16 = redis::from_redis_value(master.get(1)?).ok()?;
+            Some(SlotOwner {
+                start: redis::from_redis_value(range.first()?).ok()?,
+                end: redis::from_redis_value(range.get(1)?).ok()?,
+                node: format!("{}:{}", ip, port),
+            })
+        })
+        .collect();
 
-        // Process each part of the key
-        for (i, &part) in parts.iter().enumerate() {
-            let is_last = i == parts.len() - 1;
+    Ok(Some(owners))
+}
 
-            // Build the current path
-            if !current_path.is_empty() {
-                current_path.push(':');
-            }
-            current_path.push_str(part);
+async fn convert_keys_to_tree(
+    client: &mut RedisConnection,
+    tree: &KeyTree,
+    keys: Vec<String>,
+    slot_owners: Option<&[SlotOwner]>,
+    details: bool,
+) -> Vec<RedisTreeItem> {
+    let metas = get_key_metas(client, &tree.cache, &keys, slot_owners, details).await;
+    build_tree(tree, keys, &metas, slot_owners)
+}
 
-            // Check if this part already exists in current_items
-            let existing_index = current_items.iter().position(|item| item.label == part);
+/// Lays out `keys` as a tree of folders, each with the number of keys and
+/// the memory usage of the keys inside it.
+fn build_tree(
+    tree: &KeyTree,
+    keys: Vec<String>,
+    metas: &HashMap<String, KeyMeta>,
+    slot_owners: Option<&[SlotOwner]>,
+) -> Vec<RedisTreeItem> {
+    let mut root_items: Vec<RedisTreeItem> = Vec::new();
 
-            match existing_index {
-                Some(index) => {
-                    // Item exists, move to its children
-                    current_items = current_items[index].children.get_or_insert(Vec::new());
-                }
+    for key in keys {
+        let meta = metas.get(&key);
+        let memory_usage = meta
+            .and_then(|meta| meta.details.as_ref())
+            .and_then(|details| details.memory_usage);
+        let mut current_items = &mut root_items;
+        let mut rest = key.as_str();
+
+        // Walk down the folders of the key, creating the missing ones. Folders
+        // are told apart by their prefix, as the same name may follow
+        // different delimiters.
+        while let Some((folder, _, next)) = tree.split(rest) {
+            let prefix = &key[..key.len() - next.len()];
+            let index = match current_items
+                .iter()
+                .position(|item| item.kind == TreeItemKind::Folder && item.key == prefix)
+            {
+                Some(index) => index,
                 None => {
-                    // Create new item
-                    let new_item = RedisTreeItem {
-                        key: if is_last { key.clone() } else { "".to_string() },
-                        label: part.to_string(),
-                        children: if is_last { None } else { Some(Vec::new()) },
-                        item_type: item_type.clone(),
-                    };
-
-                    current_items.push(new_item);
-
-                    // If not the last part, get the children vec of the newly added item
-                    if !is_last {
-                        let last_index = current_items.len() - 1;
-                        current_items = current_items[last_index].children.as_mut().unwrap();
-                    }
+                    current_items.push(folder_item(prefix, folder, Some(Vec::new())));
+                    current_items.len() - 1
                 }
+            };
+
+            let item = &mut current_items[index];
+            item.key_count = Some(item.key_count.unwrap_or_default() + 1);
+            if let Some(memory_usage) = memory_usage {
+                item.memory_usage = Some(item.memory_usage.unwrap_or_default() + memory_usage);
             }
+            current_items = item.children.get_or_insert_with(Vec::new);
+            rest = next;
         }
+
+        current_items.push(key_item(&key, rest, meta, slot_owners));
     }
 
     root_items
 }
 
-fn get_key_type(client: &mut redis::Connection, key: String) -> Result<String, String> {
-    let key_type: String = client
-        .key_type(&key)
-        .map_err(|e| format!("Failed to get key type: {}", e))?;
+/// A folder for the keys that start with `prefix`, none counted yet.
+fn folder_item(prefix: &str, label: &str, children: Option<Vec<RedisTreeItem>>) -> RedisTreeItem {
+    RedisTreeItem {
+        key: prefix.to_string(),
+        label: label.to_string(),
+        kind: TreeItemKind::Folder,
+        children,
+        item_type: String::new(),
+        slot: None,
+        node: None,
+        ttl: None,
+        memory_usage: None,
+        key_count: Some(0),
+    }
+}
+
+/// The tree item of `key`, shown as `label`.
+fn key_item(
+    key: &str,
+    label: &str,
+    meta: Option<&KeyMeta>,
+    slot_owners: Option<&[SlotOwner]>,
+) -> RedisTreeItem {
+    let (slot, node) = key_location(key, slot_owners);
+    let details = meta.and_then(|meta| meta.details.as_ref());
+    RedisTreeItem {
+        key: key.to_string(),
+        label: label.to_string(),
+        kind: TreeItemKind::Key,
+        children: None,
+        item_type: meta.map(|meta| meta.key_type.clone()).unwrap_or_default(),
+        slot,
+        node,
+        ttl: details.map(KeyDetails::ttl),
+        memory_usage: details.and_then(|details| details.memory_usage),
+        key_count: None,
+    }
+}
+
+/// Hash slot of `key` and the master serving it, on a cluster.
+fn key_location(key: &str, slot_owners: Option<&[SlotOwner]>) -> (Option<u16>, Option<String>) {
+    let slot = slot_owners.map(|_| get_slot(key.as_bytes()));
+    let node = slot_owners.zip(slot).and_then(|(owners, slot)| {
+        owners
+            .iter()
+            .find(|owner| owner.start <= slot && slot <= owner.end)
+            .map(|owner| owner.node.clone())
+    });
+    (slot, node)
+}
+
+/// Type, and TTL and memory usage with `details`, of each of `keys`. Taken
+/// from `cache` where possible, the rest is looked up in pipelined batches,
+/// one node at a time on a cluster. Keys that could not be looked up are
+/// missing from the result.
+async fn get_key_metas(
+    client: &mut RedisConnection,
+    cache: &KeyCache,
+    keys: &[String],
+    slot_owners: Option<&[SlotOwner]>,
+    details: bool,
+) -> HashMap<String, KeyMeta> {
+    let mut metas = cache.get_all(keys, details);
+
+    let mut keys_by_node: HashMap<Option<String>, Vec<&str>> = HashMap::new();
+    for key in keys.iter().filter(|key| !metas.contains_key(*key)) {
+        let (_, node) = key_location(key, slot_owners);
+        keys_by_node.entry(node).or_default().push(key);
+    }
+
+    for (node, keys) in keys_by_node {
+        for batch in keys.chunks(KEY_META_BATCH_SIZE) {
+            let mut fetched = fetch_key_metas(client, batch, node.as_deref(), details).await;
+            if details && fetched.is_err() {
+                // MEMORY USAGE may be disabled on managed servers, still show
+                // the types then.
+                fetched = fetch_key_metas(client, batch, node.as_deref(), false).await;
+            }
+
+            match fetched {
+                Ok(fetched) => {
+                    cache.insert_all(&fetched);
+                    metas.extend(fetched);
+                }
+                Err(e) => warn!("Failed to get key types: {}", e),
+            }
+        }
+    }
+
+    metas
+}
 
-    Ok(key_type)
+async fn fetch_key_metas(
+    client: &mut RedisConnection,
+    keys: &[&str],
+    node: Option<&str>,
+    details: bool,
+) -> RedisResult<Vec<(String, KeyMeta)>> {
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("TYPE").arg(*key);
+        if details {
+            pipe.cmd("PTTL").arg(*key);
+            pipe.cmd("MEMORY").arg("USAGE").arg(*key);
+        }
+    }
+    let values = client.query_pipeline_node(&pipe, node).await?;
+
+    let replies_per_key = if details { 3 } else { 1 };
+    keys.iter()
+        .zip(values.chunks(replies_per_key))
+        .map(|(key, replies)| {
+            let key_details = match replies {
+                [_, pttl, memory_usage] => Some(KeyDetails::new(
+                    i64::from_redis_value(pttl)?,
+                    Option::<u64>::from_redis_value(memory_usage)?,
+                )),
+                _ => None,
+            };
+            let meta = KeyMeta {
+                key_type: String::from_redis_value(&replies[0])?,
+                details: key_details,
+            };
+            Ok((key.to_string(), meta))
+        })
+        .collect()
 }
 
-fn get_string(client: &mut redis::Connection, key: String) -> Result<String, String> {
+async fn get_string(client: &mut RedisConnection, key: String) -> Result<String, String> {
     let value: String = client
         .get(&key)
+        .await
         .map_err(|e| format!("Failed to get key value: {}", e))?;
 
     Ok(value)
 }
 
-fn get_list(client: &mut redis::Connection, key: String) -> Result<Vec<String>, String> {
+async fn get_list(client: &mut RedisConnection, key: String) -> Result<Vec<String>, String> {
     let value: Vec<String> = client
         .lrange(&key, 0, -1)
+        .await
         .map_err(|e| format!("Failed to get list: {}", e))?;
 
     Ok(value)
 }
 
-fn get_set(client: &mut redis::Connection, key: String) -> Result<Vec<String>, String> {
+async fn get_set(client: &mut RedisConnection, key: String) -> Result<Vec<String>, String> {
     let value: Vec<String> = client
         .smembers(&key)
+        .await
         .map_err(|e| format!("Failed to get set: {}", e))?;
 
     Ok(value)
 }
 
-fn get_hash(
-    client: &mut redis::Connection,
+async fn get_hash(
+    client: &mut RedisConnection,
     key: String,
 ) -> Result<HashMap<String, String>, String> {
     let value: HashMap<String, String> = client
         .hgetall(&key)
+        .await
         .map_err(|e| format!("Failed to get hash: {}", e))?;
     Ok(value)
 }
 
-fn get_stream(
-    client: &mut redis::Connection,
+async fn get_stream(
+    client: &mut RedisConnection,
     key: String,
 ) -> Result<Vec<HashMap<String, HashMap<String, String>>>, String> {
     let value: Vec<HashMap<String, HashMap<String, String>>> = client
         .xrange(&key, "-", "+")
+        .await
         .map_err(|e| format!("Failed to get stream: {}", e))?;
     Ok(value)
 }
 
-fn get_zset(client: &mut redis::Connection, key: String) -> Result<Vec<(String, f64)>, String> {
+async fn get_zset(client: &mut RedisConnection, key: String) -> Result<Vec<(String, f64)>, String> {
     let value: Vec<(String, f64)> = client
         .zrange_withscores(&key, 0, -1)
+        .await
         .map_err(|e| format!("Failed to get zset: {}", e))?;
-    println!("zset value: {:?}", value);
     Ok(value)
 }
 
@@ -925,3 +1560,146 @@ fn format_time_duration(total_seconds: u64) -> String {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delimited_by(delimiters: &[&str]) -> KeyTree {
+        KeyTree {
+            cache: KeyCache::default(),
+            delimiters: delimiters.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// Metas of `keys` with the given memory usage.
+    fn metas(keys: &[(&str, u64)]) -> HashMap<String, KeyMeta> {
+        keys.iter()
+            .map(|(key, memory_usage)| {
+                let meta = KeyMeta {
+                    key_type: "string".to_string(),
+                    details: Some(KeyDetails::new(-1, Some(*memory_usage))),
+                };
+                (key.to_string(), meta)
+            })
+            .collect()
+    }
+
+    fn find<'a>(items: &'a [RedisTreeItem], kind: TreeItemKind, key: &str) -> &'a RedisTreeItem {
+        items
+            .iter()
+            .find(|item| item.kind == kind && item.key == key)
+            .unwrap_or_else(|| panic!("no {:?} '{}' in the tree", kind, key))
+    }
+
+    fn children(item: &RedisTreeItem) -> &[RedisTreeItem] {
+        item.children.as_deref().unwrap_or_default()
+    }
+
+    #[test]
+    fn key_sits_next_to_the_folder_it_prefixes() {
+        let items = build_tree(
+            &delimited_by(&[":"]),
+            keys(&["a:b", "a:b:c"]),
+            &HashMap::new(),
+            None,
+        );
+
+        assert_eq!(items.len(), 1);
+        let a = find(&items, TreeItemKind::Folder, "a:");
+        assert_eq!(a.key_count, Some(2));
+        assert_eq!(children(a).len(), 2);
+
+        let key = find(children(a), TreeItemKind::Key, "a:b");
+        assert_eq!(key.label, "b");
+        let folder = find(children(a), TreeItemKind::Folder, "a:b:");
+        assert_eq!(folder.label, "b");
+        assert_eq!(folder.key_count, Some(1));
+        assert_eq!(
+            find(children(folder), TreeItemKind::Key, "a:b:c").label,
+            "c"
+        );
+    }
+
+    #[test]
+    fn folders_total_the_keys_inside_them() {
+        let tree_keys = [
+            ("user:1", 10),
+            ("user:2", 20),
+            ("user:admin:1", 30),
+            ("session", 5),
+        ];
+        let items = build_tree(
+            &delimited_by(&[":"]),
+            tree_keys.iter().map(|(key, _)| key.to_string()).collect(),
+            &metas(&tree_keys),
+            None,
+        );
+
+        let user = find(&items, TreeItemKind::Folder, "user:");
+        assert_eq!(user.key_count, Some(3));
+        assert_eq!(user.memory_usage, Some(60));
+        let admin = find(children(user), TreeItemKind::Folder, "user:admin:");
+        assert_eq!(admin.key_count, Some(1));
+        assert_eq!(admin.memory_usage, Some(30));
+        assert_eq!(
+            find(&items, TreeItemKind::Key, "session").memory_usage,
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn folders_without_memory_usage_leave_it_unset() {
+        let items = build_tree(
+            &delimited_by(&[":"]),
+            keys(&["user:1", "user:2"]),
+            &HashMap::new(),
+            None,
+        );
+
+        let user = find(&items, TreeItemKind::Folder, "user:");
+        assert_eq!(user.key_count, Some(2));
+        assert_eq!(user.memory_usage, None);
+    }
+
+    #[test]
+    fn same_name_after_different_delimiters_makes_two_folders() {
+        let items = build_tree(
+            &delimited_by(&[":", "/"]),
+            keys(&["a:x", "a/y", "a:z"]),
+            &HashMap::new(),
+            None,
+        );
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(find(&items, TreeItemKind::Folder, "a:").key_count, Some(2));
+        assert_eq!(find(&items, TreeItemKind::Folder, "a/").key_count, Some(1));
+    }
+
+    /// With RESP3 the key readers get maps, sets and doubles instead of flat
+    /// arrays and strings, which must decode into the same values.
+    #[test]
+    fn key_values_decode_from_resp3_replies() {
+        use redis::Value;
+        let bulk = |value: &str| Value::BulkString(value.as_bytes().to_vec());
+
+        let hash: HashMap<String, String> =
+            redis::from_redis_value(&Value::Map(vec![(bulk("field"), bulk("value"))])).unwrap();
+        assert_eq!(hash["field"], "value");
+
+        let set: Vec<String> =
+            redis::from_redis_value(&Value::Set(vec![bulk("a"), bulk("b")])).unwrap();
+        assert_eq!(set, ["a", "b"]);
+
+        let zset: Vec<(String, f64)> = redis::from_redis_value(&Value::Array(vec![
+            Value::Array(vec![bulk("a"), Value::Double(1.5)]),
+            Value::Array(vec![bulk("b"), Value::Double(-2.0)]),
+        ]))
+        .unwrap();
+        assert_eq!(zset, [("a".to_string(), 1.5), ("b".to_string(), -2.0)]);
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use tadis_database::{
+    crypto::{Credentials, PassphraseKey},
+    models::ConnectionConfig,
+};
+
+use crate::models::connection::ExportFormat;
+
+/// Identifies one of our exports among other JSON or TOML files.
+const EXPORT_FORMAT: &str = "tadis-connections";
+/// Bumped when the layout changes in a way older versions cannot read.
+const EXPORT_VERSION: u32 = 1;
+
+/// A shareable list of connections. Secrets are left out unless a passphrase
+/// encrypts them, in which case `encryption` holds what is needed to derive
+/// its key again.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportFile {
+    format: String,
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption: Option<PassphraseKey>,
+    connections: Vec<ConnectionConfig>,
+}
+
+/// Serializes `configs`, whose secrets are encrypted with the local
+/// `credentials`, re-encrypting the secrets under `passphrase` or dropping
+/// them when there is none.
+pub fn export(
+    mut configs: Vec<ConnectionConfig>,
+    credentials: &Credentials,
+    format: ExportFormat,
+    passphrase: Option<&str>,
+) -> Result<String, String> {
+    // Ids and timestamps are local to this database, imports get their own.
+    for config in configs.iter_mut() {
+        config.id = None;
+        config.created_at = None;
+        config.updated_at = None;
+        config.deleted_at = None;
+        config.last_connected_at = None;
+    }
+    let encryption = match passphrase {
+        Some(passphrase) => {
+            let (key, cipher) = PassphraseKey::create(passphrase)?;
+            for config in configs.iter_mut() {
+                credentials.decrypt_config(config)?;
+                cipher.encrypt_config(config)?;
+            }
+            Some(key)
+        }
+        None => {
+            configs.iter_mut().for_each(strip_secrets);
+            None
+        }
+    };
+
+    let file = ExportFile {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        encryption,
+        connections: configs,
+    };
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize connections: {}", e)),
+        ExportFormat::Toml => toml::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to serialize connections: {}", e)),
+    }
+}
+
+/// Reads an export written by `export`, in either format. Returns `None` when
+/// `source` is not one, so other import formats can be tried. Secrets come
+/// back in plain text.
+pub fn parse(
+    source: &str,
+    passphrase: Option<&str>,
+) -> Option<Result<Vec<ConnectionConfig>, String>> {
+    let file = serde_json::from_str::<ExportFile>(source)
+        .ok()
+        .or_else(|| toml::from_str::<ExportFile>(source).ok())
+        .filter(|file| file.format == EXPORT_FORMAT)?;
+
+    Some(read_export(file, passphrase))
+}
+
+fn read_export(
+    file: ExportFile,
+    passphrase: Option<&str>,
+) -> Result<Vec<ConnectionConfig>, String> {
+    if file.version > EXPORT_VERSION {
+        return Err(format!(
+            "This file was exported by a newer version of the app (format version {})",
+            file.version
+        ));
+    }
+
+    let mut configs = file.connections;
+    if let Some(encryption) = file.encryption {
+        let passphrase = passphrase
+            .ok_or("This file's passwords are encrypted, enter its password to import it")?;
+        let cipher = encryption.unlock(passphrase)?;
+        for config in configs.iter_mut() {
+            cipher.decrypt_config(config)?;
+        }
+    }
+
+    Ok(configs)
+}
+
+fn strip_secrets(config: &mut ConnectionConfig) {
+    config.password.clear();
+    if let Some(ssh) = config.ssh.as_mut() {
+        ssh.password = None;
+        ssh.passphrase = None;
+    }
+    if let Some(sentinel) = config.sentinel.as_mut() {
+        sentinel.password = None;
+    }
+    if let Some(proxy) = config.proxy.as_mut() {
+        proxy.password = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tadis_database::models::{ProxyConfig, SshConfig};
+
+    use super::*;
+    use crate::import;
+
+    /// Credentials with a random key, as on a first start.
+    fn credentials(name: &str) -> Credentials {
+        let dir =
+            std::env::temp_dir().join(format!("tadis-export-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key_path = dir.join("credentials.key");
+        let _ = std::fs::remove_file(&key_path);
+        Credentials::load(key_path, false).unwrap()
+    }
+
+    /// A stored connection, with its secrets encrypted under `credentials`.
+    fn stored(credentials: &Credentials) -> ConnectionConfig {
+        let mut config = ConnectionConfig {
+            id: Some(7),
+            name: "prod".to_string(),
+            host: "cache.internal".to_string(),
+            port: 6380,
+            password: "secret".to_string(),
+            ssh: Some(SshConfig {
+                host: "bastion.internal".to_string(),
+                port: 22,
+                passphrase: Some("key-passphrase".to_string()),
+                ..Default::default()
+            }),
+            proxy: Some(ProxyConfig {
+                host: "proxy.internal".to_string(),
+                port: 1080,
+                password: Some("proxy-secret".to_string()),
+                ..Default::default()
+            }),
+            created_at: Some("2026-10-18 10:00:00".to_string()),
+            ..Default::default()
+        };
+        credentials.encrypt_config(&mut config).unwrap();
+        config
+    }
+
+    fn import_one(source: &str, passphrase: Option<&str>) -> Result<ConnectionConfig, String> {
+        let mut entries = import::parse(source, passphrase)?;
+        assert_eq!(entries.len(), 1);
+        entries.remove(0)
+    }
+
+    fn round_trips_under_a_passphrase(format: ExportFormat) {
+        let credentials = credentials(&format!("{:?}", format));
+        let exported = export(
+            vec![stored(&credentials)],
+            &credentials,
+            format,
+            Some("team passphrase"),
+        )
+        .unwrap();
+        assert!(!exported.contains("secret"));
+
+        let config = import_one(&exported, Some("team passphrase")).unwrap();
+        assert_eq!(config.name, "prod");
+        assert_eq!(config.host, "cache.internal");
+        assert_eq!(config.port, 6380);
+        assert_eq!(config.id, None);
+        assert_eq!(config.created_at, None);
+        assert_eq!(config.password, "secret");
+        let ssh = config.ssh.unwrap();
+        assert_eq!(ssh.password, None);
+        assert_eq!(ssh.passphrase.as_deref(), Some("key-passphrase"));
+        assert_eq!(
+            config.proxy.unwrap().password.as_deref(),
+            Some("proxy-secret")
+        );
+    }
+
+    #[test]
+    fn json_round_trips_under_a_passphrase() {
+        round_trips_under_a_passphrase(ExportFormat::Json);
+    }
+
+    #[test]
+    fn toml_round_trips_under_a_passphrase() {
+        round_trips_under_a_passphrase(ExportFormat::Toml);
+    }
+
+    #[test]
+    fn strips_secrets_without_a_passphrase() {
+        let credentials = credentials("stripped");
+        let exported = export(
+            vec![stored(&credentials)],
+            &credentials,
+            ExportFormat::Json,
+            None,
+        )
+        .unwrap();
+        assert!(!exported.contains("enc:v1:"));
+        assert!(!exported.contains("encryption"));
+
+        let config = import_one(&exported, None).unwrap();
+        assert_eq!(config.host, "cache.internal");
+        assert_eq!(config.password, "");
+        assert_eq!(config.ssh.unwrap().passphrase, None);
+        assert_eq!(config.proxy.unwrap().password, None);
+    }
+
+    #[test]
+    fn rejects_a_wrong_or_missing_passphrase() {
+        let credentials = credentials("wrong-passphrase");
+        let exported = export(
+            vec![stored(&credentials)],
+            &credentials,
+            ExportFormat::Toml,
+            Some("team passphrase"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            import_one(&exported, Some("another passphrase")).unwrap_err(),
+            "Incorrect password"
+        );
+        assert!(import_one(&exported, None).is_err());
+    }
+
+    #[test]
+    fn rejects_exports_of_newer_versions() {
+        let exported = format!(
+            r#"{{"format": "{}", "version": {}, "connections": []}}"#,
+            EXPORT_FORMAT,
+            EXPORT_VERSION + 1
+        );
+
+        assert!(import::parse(&exported, None).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::client::{open_connection, RedisConnection};
+use crate::models::connection::ConnectionStatusEvent;
+use crate::AppState;
+
+pub const CONNECTION_LOST_EVENT: &str = "connection-lost";
+pub const CONNECTION_RESTORED_EVENT: &str = "connection-restored";
+
+/// How often the checker wakes up to see which connections are due a ping.
+const CHECK_TICK: Duration = Duration::from_secs(1);
+pub const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pings every live connection on its keepalive interval, reopening the ones
+/// that stopped answering and telling the UI when they go down and come back.
+pub fn spawn(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let health: Arc<Mutex<Health>> = Arc::default();
+        let mut last_checked: HashMap<String, Instant> = HashMap::new();
+        loop {
+            tokio::time::sleep(CHECK_TICK).await;
+            check_due(&app_handle, &health, &mut last_checked);
+        }
+    });
+}
+
+/// Connections found down, and those with a check still running. Shared with
+/// the check tasks.
+#[derive(Default)]
+struct Health {
+    down: HashSet<String>,
+    checking: HashSet<String>,
+}
+
+/// Starts a check of every connection due one, each in its own task so an
+/// unreachable server does not hold up the others.
+fn check_due(
+    app_handle: &AppHandle,
+    health: &Arc<Mutex<Health>>,
+    last_checked: &mut HashMap<String, Instant>,
+) {
+    let state = app_handle.state::<Mutex<AppState>>();
+    let Ok(clients) = state.lock().map(|state| {
+        state
+            .connections
+            .iter()
+            .map(|(name, live)| {
+                (
+                    name.clone(),
+                    live.client.clone(),
+                    live.config.keepalive_interval(),
+                )
+            })
+            .collect::<Vec<(String, RedisConnection, Duration)>>()
+    }) else {
+        return;
+    };
+    let mut health_state = health.lock().unwrap_or_else(PoisonError::into_inner);
+    health_state
+        .down
+        .retain(|name| clients.iter().any(|(client_name, ..)| client_name == name));
+    last_checked.retain(|name, _| clients.iter().any(|(client_name, ..)| client_name == name));
+
+    for (name, client, interval) in clients {
+        // New connections were just opened, so their first ping waits a full interval.
+        let checked = last_checked
+            .entry(name.clone())
+            .or_insert_with(Instant::now);
+        if checked.elapsed() < interval || !health_state.checking.insert(name.clone()) {
+            continue;
+        }
+        *checked = Instant::now();
+
+        tauri::async_runtime::spawn(check(app_handle.clone(), health.clone(), name, client));
+    }
+}
+
+/// Pings `name` and reconnects it if it did not answer.
+async fn check(
+    app_handle: AppHandle,
+    health: Arc<Mutex<Health>>,
+    name: String,
+    mut client: RedisConnection,
+) {
+    let result = if client.ping(PING_TIMEOUT).await {
+        None
+    } else {
+        let state = app_handle.state::<Mutex<AppState>>();
+        Some(reconnect(&state, &name).await)
+    };
+
+    let mut health = health.lock().unwrap_or_else(PoisonError::into_inner);
+    health.checking.remove(&name);
+    let Some(result) = result else {
+        // A command may have reconnected it before the next check did.
+        if health.down.remove(&name) {
+            notify(&app_handle, CONNECTION_RESTORED_EVENT, &name, None);
+        }
+        return;
+    };
+
+    if health.down.insert(name.clone()) {
+        warn!("Connection '{}' stopped responding", name);
+        notify(
+            &app_handle,
+            CONNECTION_LOST_EVENT,
+            &name,
+            result.clone().err(),
+        );
+    }
+
+    match result {
+        Ok(_) => {
+            info!("Reconnected '{}'", name);
+            health.down.remove(&name);
+            notify(&app_handle, CONNECTION_RESTORED_EVENT, &name, None);
+        }
+        Err(e) => warn!("Failed to reconnect '{}': {}", name, e),
+    }
+}
+
+/// Reopens `name` on the database it has selected and returns the new handle.
+/// The state lock is only taken around the lookup and the swap.
+pub async fn reconnect(state: &Mutex<AppState>, name: &str) -> Result<RedisConnection, String> {
+    let config = state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .reconnect_config(name)
+        .ok_or(format!("Connection '{}' is not connected.", name))?;
+
+    let (client, tunnel) = open_connection(&config).await?;
+
+    state
+        .lock()
+        .map_err(|e| format!("Failed to lock state: {}", e))?
+        .replace_connection(name, client.clone(), tunnel);
+
+    Ok(client)
+}
+
+fn notify(app_handle: &AppHandle, event: &str, name: &str, error: Option<String>) {
+    let payload = ConnectionStatusEvent {
+        name: name.to_string(),
+        error,
+    };
+    if let Err(e) = app_handle.emit(event, payload) {
+        warn!("Failed to emit '{}': {}", event, e);
+    }
+}
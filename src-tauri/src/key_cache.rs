@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+
+/// Keys remembered per connection. The least recently used are dropped
+/// beyond that, so browsing a huge keyspace does not grow the cache forever.
+const MAX_ENTRIES: NonZeroUsize = match NonZeroUsize::new(100_000) {
+    Some(max) => max,
+    None => unreachable!(),
+};
+
+/// Type, and optionally TTL and memory usage, of the keys of one connection
+/// already looked up, so building the key tree again does not ask the server
+/// about every key.
+///
+/// Clones share the same cache. An entry is dropped when its key is written
+/// through the app or it falls out of the cache, and all of them when another
+/// database is selected or the connection is reopened. Types changed by other
+/// clients stay stale until then.
+#[derive(Clone)]
+pub struct KeyCache {
+    entries: Arc<Mutex<LruCache<String, KeyMeta>>>,
+}
+
+impl Default for KeyCache {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(LruCache::new(MAX_ENTRIES))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyMeta {
+    pub key_type: String,
+    /// Only looked up when asked for, as it costs two more commands per key.
+    pub details: Option<KeyDetails>,
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyDetails {
+    /// When the key expires, `None` if it never does. Kept as a deadline so the
+    /// cached TTL keeps counting down.
+    expires_at: Option<Instant>,
+    pub memory_usage: Option<u64>,
+}
+
+impl KeyDetails {
+    /// From a `PTTL` reply, which is negative for keys without expiry.
+    pub fn new(pttl: i64, memory_usage: Option<u64>) -> Self {
+        let expires_at = u64::try_from(pttl)
+            .ok()
+            .map(|pttl| Instant::now() + Duration::from_millis(pttl));
+        Self {
+            expires_at,
+            memory_usage,
+        }
+    }
+
+    /// Seconds left to live, `-1` for keys without expiry, like `TTL`.
+    pub fn ttl(&self) -> i64 {
+        match self.expires_at {
+            Some(expires_at) => expires_at
+                .saturating_duration_since(Instant::now())
+                .as_secs() as i64,
+            None => -1,
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Instant::now())
+    }
+}
+
+impl KeyCache {
+    /// The cached entries of `keys`. With `details`, entries cached without
+    /// them, or whose TTL ran out, count as missing.
+    pub fn get_all(&self, keys: &[String], details: bool) -> HashMap<String, KeyMeta> {
+        let mut entries = self.entries();
+        keys.iter()
+            .filter_map(|key| {
+                let meta = entries.get(key)?;
+                let usable = match &meta.details {
+                    Some(key_details) => !key_details.expired(),
+                    None => !details,
+                };
+                usable.then(|| (key.clone(), meta.clone()))
+            })
+            .collect()
+    }
+
+    pub fn insert_all(&self, metas: &[(String, KeyMeta)]) {
+        let mut entries = self.entries();
+        for (key, meta) in metas {
+            entries.put(key.clone(), meta.clone());
+        }
+    }
+
+    pub fn invalidate(&self, key: &str) {
+        self.entries().pop(key);
+    }
+
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    /// A panic while the lock was held cannot leave the cache inconsistent,
+    /// so a poisoned lock is taken over rather than failing every lookup.
+    fn entries(&self) -> MutexGuard<'_, LruCache<String, KeyMeta>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -1,10 +1,26 @@
-use redis::Connection;
-use std::{collections::HashMap, sync::Mutex};
-use tauri::Manager;
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+};
+use client::RedisConnection;
+use key_cache::KeyCache;
+use tadis_database::models::ConnectionConfig;
+use tauri::{Manager, WindowEvent};
+use sentinel::SentinelWatcher;
+use tunnel::Tunnel;
 use window_vibrancy::*;
 
+mod client;
 mod commands;
+mod export;
+mod health;
+mod import;
+mod key_cache;
 mod models;
+mod proxy;
+mod sentinel;
+mod tls;
+mod tunnel;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -15,10 +31,23 @@ pub fn run() {
         .plugin(tadis_database::plugin::Builder::new().build())
         .invoke_handler(tauri::generate_handler![
             commands::connection::delete_connection_config,
+            commands::connection::load_deleted_connection_configs,
+            commands::connection::restore_connection_config,
+            commands::connection::purge_connection_configs,
             commands::connection::save_connection_config,
             commands::connection::load_connection_config,
+            commands::connection::reorder_connection_configs,
             commands::connection::test_connection,
             commands::connection::connect_to_redis,
+            commands::connection::select_database,
+            commands::connection::disconnect,
+            commands::connection::switch_connection,
+            commands::connection::list_active_connections,
+            commands::connection::import_connections,
+            commands::connection::export_connections,
+            commands::credentials::get_credentials_status,
+            commands::credentials::unlock_credentials,
+            commands::credentials::set_master_password,
             commands::redis::get_all_keys_as_tree,
             commands::redis::search_keys_as_tree,
             commands::redis::get_key_detail,
@@ -45,7 +74,18 @@ pub fn run() {
             commands::redis::add_zset_items,
             commands::redis::get_server_statistics,
             commands::redis::get_client_list,
+            commands::redis::scan_keys,
+            commands::redis::cancel_scan,
+            commands::redis::get_prefix_children,
         ])
+        .on_window_event(|window, event| {
+            if let WindowEvent::Destroyed = event {
+                let state = window.state::<Mutex<AppState>>();
+                if let Ok(mut state) = state.lock() {
+                    state.selected_clients.remove(window.label());
+                };
+            }
+        })
         .setup(|app| {
             let window = app.get_webview_window("main").unwrap();
 
@@ -58,10 +98,13 @@ pub fn run() {
                 .expect("Unsupported platform! 'apply_blur' is only supported on Windows");
 
             app.manage(Mutex::new(AppState {
-                connected_clients: HashMap::new(),
-                selected_client: String::new(),
+                connections: HashMap::new(),
+                selected_clients: HashMap::new(),
+                scans: HashMap::new(),
             }));
 
+            health::spawn(app.handle().clone());
+
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -69,7 +112,120 @@ pub fn run() {
 }
 
 pub struct AppState {
-    pub connected_clients: HashMap<String, Connection>,
-    pub selected_client: String,
+    pub connections: HashMap<String, LiveConnection>,
+    /// Connection each webview works against when a command names none,
+    /// keyed by webview label.
+    pub selected_clients: HashMap<String, String>,
+    /// Cancellation flags of the key scans in progress, keyed by scan id.
+    pub scans: HashMap<String, Arc<AtomicBool>>,
+}
+
+/// A connected server and everything that lives exactly as long as it does.
+pub struct LiveConnection {
+    pub client: RedisConnection,
+    /// Config the connection was opened with, used to reopen it.
+    pub config: ConnectionConfig,
+    /// Database currently selected, which may differ from the configured one.
+    pub database: i64,
+    /// Open SSH or proxy port forward. Declared after `client` so it is dropped
+    /// last.
+    pub tunnel: Option<Tunnel>,
+    /// Failover watcher for Sentinel connections.
+    pub sentinel_watcher: Option<SentinelWatcher>,
+    /// Types of the keys already shown in the tree.
+    pub key_cache: KeyCache,
 }
 
+impl AppState {
+    /// A handle to the named connection. Handles are cheap clones sharing the
+    /// connection, so the state lock is never held during a round trip.
+    pub fn client(&self, name: &str) -> Option<RedisConnection> {
+        self.connections.get(name).map(|live| live.client.clone())
+    }
+
+    /// Picks the connection a command runs against: `connection_id` when the
+    /// caller names one, otherwise the one selected in the calling webview.
+    pub fn resolve_client(
+        &self,
+        connection_id: Option<&str>,
+        webview_label: &str,
+    ) -> Result<(String, RedisConnection), String> {
+        let name = match connection_id {
+            Some(name) => name,
+            None => self
+                .selected_clients
+                .get(webview_label)
+                .ok_or("No client selected".to_string())?,
+        };
+        let client = self
+            .client(name)
+            .ok_or(format!("Connection '{}' is not connected.", name))?;
+
+        Ok((name.to_string(), client))
+    }
+
+    /// A handle to the key cache of the named connection.
+    pub fn key_cache(&self, name: &str) -> Option<KeyCache> {
+        self.connections.get(name).map(|live| live.key_cache.clone())
+    }
+
+    /// Whether the named connection must not be written to.
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.connections
+            .get(name)
+            .is_some_and(|live| live.config.read_only)
+    }
+
+    /// Closes the named connection along with its tunnel and watcher. Returns
+    /// whether it was connected.
+    pub fn remove_connection(&mut self, name: &str) -> bool {
+        let removed = self.connections.remove(name).is_some();
+        self.selected_clients.retain(|_, selected| selected != name);
+
+        removed
+    }
+
+    /// Moves the live connection `old` and any webview selecting it to `new`.
+    /// Returns whether it was connected.
+    pub fn rename_connection(&mut self, old: &str, new: &str) -> bool {
+        let Some(mut live) = self.connections.remove(old) else {
+            return false;
+        };
+        live.config.name = new.to_string();
+        self.connections.insert(new.to_string(), live);
+        for selected in self.selected_clients.values_mut() {
+            if selected == old {
+                *selected = new.to_string();
+            }
+        }
+
+        true
+    }
+
+    /// The config to reopen `name` with, on the database it has selected now.
+    pub fn reconnect_config(&self, name: &str) -> Option<ConnectionConfig> {
+        let live = self.connections.get(name)?;
+        let mut config = live.config.clone();
+        config.database = live.database;
+        Some(config)
+    }
+
+    /// Swaps in a reopened connection and its tunnel. Returns false, dropping
+    /// both, if `name` was disconnected in the meantime. Keys may have changed
+    /// while it was down, so the key cache starts over.
+    pub fn replace_connection(
+        &mut self,
+        name: &str,
+        client: RedisConnection,
+        tunnel: Option<Tunnel>,
+    ) -> bool {
+        let Some(live) = self.connections.get_mut(name) else {
+            return false;
+        };
+        live.client = client;
+        live.tunnel = tunnel;
+        live.key_cache.clear();
+
+        true
+    }
+}
@@ -1 +1,2 @@
+pub mod connection;
 pub mod redis;
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use tadis_database::models::Environment;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveConnection {
+    pub name: String,
+    pub database: i64,
+    pub cluster: bool,
+    /// Commands that write are rejected on this connection.
+    pub read_only: bool,
+    pub environment: Environment,
+    /// Whether the calling window runs commands against this connection by default.
+    pub selected: bool,
+}
+
+/// Payload of the connection lost and restored events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatusEvent {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialsStatus {
+    /// Whether stored secrets are keyed by a master password instead of the key file.
+    pub master_password: bool,
+    /// Whether the master password still has to be entered before connecting.
+    pub locked: bool,
+    /// Why the key file could not be loaded. The credentials stay locked
+    /// until it is fixed.
+    pub error: Option<String>,
+}
+
+/// Outcome of an import. Entries that conflict or fail are reported rather
+/// than stopping the rest.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Names created, including the new names of renamed entries.
+    pub imported: Vec<String>,
+    pub overwritten: Vec<String>,
+    /// Names that already exist, left untouched.
+    pub conflicts: Vec<String>,
+    pub failed: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFailure {
+    /// 1-based position of the entry in the import source.
+    pub entry: usize,
+    pub error: String,
+}
+
+/// What an import does with an entry whose name is already taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeStrategy {
+    /// Keep the existing connection and report the conflict.
+    #[default]
+    Skip,
+    /// Replace the existing connection. Secrets missing from the import are kept.
+    Overwrite,
+    /// Import under the first free name of the form `name (2)`.
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Toml,
+}
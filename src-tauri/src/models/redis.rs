@@ -4,10 +4,76 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RedisTreeItem {
+    /// The key itself, or for a folder the prefix, delimiter included, shared
+    /// by every key inside it.
     pub key: String,
     pub label: String,
+    pub kind: TreeItemKind,
+    /// Contents of a folder. `None` on keys, and on folders listed by prefix
+    /// whose contents are only listed when expanded.
     pub children: Option<Vec<RedisTreeItem>>,
+    /// Type of a key, empty on folders.
     pub item_type: String,
+    /// Hash slot of the key, only set on cluster connections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u16>,
+    /// `ip:port` of the master serving the key, only set on cluster connections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    /// Seconds left to live of a key, `-1` without expiry, only set when
+    /// asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i64>,
+    /// Bytes reported by `MEMORY USAGE` for a key, summed over the keys inside
+    /// a folder. Only set when asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_usage: Option<u64>,
+    /// Keys inside a folder, at any depth.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_count: Option<usize>,
+}
+
+/// Folders and keys are told apart by kind, so a key and a folder may share a
+/// label, as `a:b` and `a:b:c` do.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TreeItemKind {
+    Folder,
+    Key,
+}
+
+/// Where a key scan stopped, handed back to resume it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanCursor {
+    /// `ip:port` of each cluster master still to scan, the current one first.
+    /// Empty on a single server.
+    pub nodes: Vec<String>,
+    /// `SCAN` cursor within the current server.
+    pub cursor: u64,
+}
+
+/// One page of a key scan.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanPage {
+    pub items: Vec<RedisTreeItem>,
+    /// Resumes the scan after this page. `None` once every key was scanned.
+    pub cursor: Option<ScanCursor>,
+    /// Whether the scan stopped early because it was cancelled.
+    pub cancelled: bool,
+}
+
+/// Payload of the scan progress event, sent after every `SCAN` round trip.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgressEvent {
+    pub scan_id: String,
+    /// Keys matched by this round trip.
+    pub keys: Vec<String>,
+    /// Keys matched so far in this page.
+    pub found: usize,
+    pub cursor: Option<ScanCursor>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
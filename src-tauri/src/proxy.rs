@@ -0,0 +1,553 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{IpAddr, Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{info, warn};
+use tadis_database::models::{ProxyConfig, ProxyKind};
+
+/// Longest HTTP response header accepted from a proxy answering `CONNECT`.
+const MAX_HTTP_HEADER_LEN: usize = 16 * 1024;
+
+/// A local port forward to `remote_host:remote_port` through a SOCKS5 or
+/// HTTP `CONNECT` proxy, so the Redis client only ever dials localhost.
+///
+/// The forward stays open until the tunnel is dropped.
+pub struct ProxyTunnel {
+    local_port: u16,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl ProxyTunnel {
+    /// Connects through the proxy once up front, so a proxy that is down or
+    /// refuses the target fails here rather than as a closed Redis connection.
+    pub fn open(
+        proxy: &ProxyConfig,
+        remote_host: &str,
+        remote_port: u16,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        let target = Target {
+            proxy: proxy.clone(),
+            host: remote_host.to_string(),
+            port: remote_port,
+            timeout,
+        };
+        let first = target.connect()?;
+
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .map_err(|e| format!("Failed to bind local proxy port: {}", e))?;
+        let local_port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to bind local proxy port: {}", e))?
+            .port();
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        {
+            let shutdown = shutdown.clone();
+            thread::spawn(move || forward(listener, target, first, shutdown));
+        }
+
+        info!(
+            "{} proxy {}:{} forwarding to {}:{} on 127.0.0.1:{}",
+            proxy.kind.as_str(),
+            proxy.host,
+            proxy.port,
+            remote_host,
+            remote_port,
+            local_port
+        );
+
+        Ok(Self {
+            local_port,
+            shutdown,
+        })
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+}
+
+impl Drop for ProxyTunnel {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        // Wakes the worker from `accept` to see the flag. It is not joined, as
+        // tunnels are dropped with the app state locked.
+        if let Err(e) = TcpStream::connect(("127.0.0.1", self.local_port)) {
+            warn!(
+                "Failed to stop proxy tunnel on 127.0.0.1:{}: {}",
+                self.local_port, e
+            );
+        }
+        info!("Proxy tunnel on 127.0.0.1:{} closed", self.local_port);
+    }
+}
+
+/// Where accepted connections are forwarded to, and through which proxy.
+struct Target {
+    proxy: ProxyConfig,
+    host: String,
+    port: u16,
+    timeout: Duration,
+}
+
+impl Target {
+    /// Opens a stream to the target through the proxy, ready to carry Redis
+    /// traffic.
+    fn connect(&self) -> Result<TcpStream, String> {
+        let address = (self.proxy.host.as_str(), self.proxy.port)
+            .to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve proxy host '{}': {}", self.proxy.host, e))?
+            .next()
+            .ok_or(format!("Failed to resolve proxy host '{}'", self.proxy.host))?;
+
+        let mut stream = TcpStream::connect_timeout(&address, self.timeout)
+            .map_err(|e| format!("Failed to connect to proxy '{}': {}", self.proxy.host, e))?;
+        // Bounds the handshake. Cleared afterwards, an idle connection is fine.
+        let set_timeout = |stream: &TcpStream, timeout| {
+            stream
+                .set_read_timeout(timeout)
+                .and_then(|_| stream.set_write_timeout(timeout))
+                .map_err(|e| format!("Failed to configure proxy connection: {}", e))
+        };
+        set_timeout(&stream, Some(self.timeout))?;
+
+        match self.proxy.kind {
+            ProxyKind::Socks5 => socks5_connect(&mut stream, &self.proxy, &self.host, self.port),
+            ProxyKind::Http => http_connect(&mut stream, &self.proxy, &self.host, self.port),
+        }
+        .map_err(|e| format!("Proxy connection failed: {}", e))?;
+
+        set_timeout(&stream, None)?;
+        Ok(stream)
+    }
+}
+
+fn forward(listener: TcpListener, target: Target, first: TcpStream, shutdown: Arc<AtomicBool>) {
+    let mut first = Some(first);
+    // Clones of every open stream, shut down on exit to stop the copy threads.
+    let streams: Arc<Mutex<Vec<TcpStream>>> = Arc::default();
+
+    loop {
+        let accepted = listener.accept();
+        if shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+        match accepted {
+            Ok((local, _)) => {
+                let upstream = match first.take() {
+                    Some(stream) => Ok(stream),
+                    None => target.connect(),
+                };
+                match upstream {
+                    Ok(upstream) => {
+                        if let Err(e) = pipe(local, upstream, &streams) {
+                            warn!("Failed to prepare proxy connection: {}", e);
+                        }
+                    }
+                    Err(e) => warn!("Failed to open proxy connection: {}", e),
+                }
+            }
+            Err(e) => {
+                warn!("Proxy tunnel listener failed: {}", e);
+                break;
+            }
+        }
+    }
+
+    for stream in streams.lock().unwrap().drain(..) {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Copies bytes both ways between `local` and `upstream` on two threads. When
+/// either side closes, both are shut down.
+fn pipe(
+    local: TcpStream,
+    upstream: TcpStream,
+    streams: &Arc<Mutex<Vec<TcpStream>>>,
+) -> io::Result<()> {
+    {
+        let mut streams = streams.lock().unwrap();
+        streams.push(local.try_clone()?);
+        streams.push(upstream.try_clone()?);
+    }
+
+    for (mut from, mut to) in [
+        (local.try_clone()?, upstream.try_clone()?),
+        (upstream, local),
+    ] {
+        thread::spawn(move || {
+            let _ = io::copy(&mut from, &mut to);
+            let _ = from.shutdown(Shutdown::Both);
+            let _ = to.shutdown(Shutdown::Both);
+        });
+    }
+
+    Ok(())
+}
+
+/// RFC 1928 `CONNECT`, with RFC 1929 username/password authentication when a
+/// username is set. Host names are sent as is for the proxy to resolve.
+fn socks5_connect(
+    stream: &mut TcpStream,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), String> {
+    let username = proxy.username.as_deref().filter(|u| !u.is_empty());
+    let greeting: &[u8] = if username.is_some() {
+        &[5, 2, 0x00, 0x02]
+    } else {
+        &[5, 1, 0x00]
+    };
+    stream.write_all(greeting).map_err(io_error)?;
+
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).map_err(io_error)?;
+    if choice[0] != 5 {
+        return Err("Not a SOCKS5 proxy".to_string());
+    }
+    match choice[1] {
+        0x00 => {}
+        0x02 => {
+            let username = username.ok_or("Proxy requires a username and password")?;
+            let password = proxy.password.as_deref().unwrap_or("");
+            if username.len() > 255 || password.len() > 255 {
+                return Err("Proxy username and password must be at most 255 bytes".to_string());
+            }
+
+            let mut request = vec![1, username.len() as u8];
+            request.extend(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend(password.as_bytes());
+            stream.write_all(&request).map_err(io_error)?;
+
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).map_err(io_error)?;
+            if status[1] != 0 {
+                return Err("Proxy authentication failed".to_string());
+            }
+        }
+        _ => return Err("Proxy accepts none of the offered authentication methods".to_string()),
+    }
+
+    let mut request = vec![5, 1, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend(ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend(ip.octets());
+        }
+        Err(_) => {
+            if host.len() > 255 {
+                return Err("Host name is too long for SOCKS5".to_string());
+            }
+            request.push(3);
+            request.push(host.len() as u8);
+            request.extend(host.as_bytes());
+        }
+    }
+    request.extend(port.to_be_bytes());
+    stream.write_all(&request).map_err(io_error)?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).map_err(io_error)?;
+    if reply[1] != 0 {
+        return Err(socks5_reply_error(reply[1]));
+    }
+
+    // The bound address that follows is of no use to us but must be consumed.
+    let address_len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).map_err(io_error)?;
+            len[0] as usize
+        }
+        other => return Err(format!("Proxy replied with unknown address type {}", other)),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).map_err(io_error)?;
+
+    Ok(())
+}
+
+fn socks5_reply_error(code: u8) -> String {
+    let reason = match code {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    };
+    format!("Proxy could not reach the server: {} ({})", reason, code)
+}
+
+/// `CONNECT host:port`, with basic authentication when a username is set.
+fn http_connect(
+    stream: &mut TcpStream,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), String> {
+    let authority = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(_)) => format!("[{}]:{}", host, port),
+        _ => format!("{}:{}", host, port),
+    };
+
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some(username) = proxy.username.as_deref().filter(|u| !u.is_empty()) {
+        let credentials = format!("{}:{}", username, proxy.password.as_deref().unwrap_or(""));
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            STANDARD.encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).map_err(io_error)?;
+
+    // Read byte by byte so nothing after the header, which belongs to Redis,
+    // is consumed.
+    let mut header = Vec::new();
+    let mut byte = [0u8; 1];
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_HTTP_HEADER_LEN {
+            return Err("Proxy response header is too long".to_string());
+        }
+        stream.read_exact(&mut byte).map_err(io_error)?;
+        header.push(byte[0]);
+    }
+
+    let header = String::from_utf8_lossy(&header);
+    let status_line = header.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    match status {
+        s if s.starts_with('2') => Ok(()),
+        "407" => Err("Proxy authentication failed".to_string()),
+        _ => Err(format!("Proxy refused CONNECT: {}", status_line)),
+    }
+}
+
+fn io_error(e: io::Error) -> String {
+    match e.kind() {
+        ErrorKind::UnexpectedEof => "Proxy closed the connection".to_string(),
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => "Proxy did not answer in time".to_string(),
+        _ => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_HOST: &str = "redis.internal";
+    const TARGET_PORT: u16 = 6379;
+
+    fn proxy_config(kind: ProxyKind, port: u16, username: Option<&str>) -> ProxyConfig {
+        ProxyConfig {
+            kind,
+            host: "127.0.0.1".to_string(),
+            port,
+            username: username.map(str::to_string),
+            password: username.map(|_| "secret".to_string()),
+        }
+    }
+
+    /// Runs `proxy` against the accepted end of a local connection, and
+    /// returns the client end.
+    fn stand_in(proxy: impl FnOnce(TcpStream) + Send + 'static) -> (TcpStream, u16) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            proxy(stream);
+        });
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        (stream, port)
+    }
+
+    fn read_bytes(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        stream.read_exact(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn read_http_header(stream: &mut TcpStream) -> String {
+        let mut header = Vec::new();
+        while !header.ends_with(b"\r\n\r\n") {
+            header.extend(read_bytes(stream, 1));
+        }
+        String::from_utf8(header).unwrap()
+    }
+
+    /// Answers a SOCKS5 `CONNECT` to the target without authentication, with
+    /// `reply` after the request, followed by `+PONG` from "Redis".
+    fn socks5_no_auth(mut stream: TcpStream, reply: &[u8]) {
+        assert_eq!(read_bytes(&mut stream, 3), [5, 1, 0]);
+        stream.write_all(&[5, 0]).unwrap();
+        read_socks5_request(&mut stream);
+        stream.write_all(reply).unwrap();
+        stream.write_all(b"+PONG\r\n").unwrap();
+    }
+
+    fn read_socks5_request(stream: &mut TcpStream) {
+        assert_eq!(read_bytes(stream, 5), [5, 1, 0, 3, TARGET_HOST.len() as u8]);
+        assert_eq!(
+            read_bytes(stream, TARGET_HOST.len()),
+            TARGET_HOST.as_bytes()
+        );
+        assert_eq!(read_bytes(stream, 2), TARGET_PORT.to_be_bytes());
+    }
+
+    /// Checks that the handshake left what followed it for Redis.
+    fn assert_pong(stream: &mut TcpStream) {
+        assert_eq!(read_bytes(stream, 7), b"+PONG\r\n");
+    }
+
+    #[test]
+    fn socks5_connects_with_authentication() {
+        let (mut stream, port) = stand_in(|mut stream| {
+            assert_eq!(read_bytes(&mut stream, 4), [5, 2, 0x00, 0x02]);
+            stream.write_all(&[5, 0x02]).unwrap();
+            assert_eq!(read_bytes(&mut stream, 7), b"\x01\x04user\x06");
+            assert_eq!(read_bytes(&mut stream, 6), b"secret");
+            stream.write_all(&[1, 0]).unwrap();
+            read_socks5_request(&mut stream);
+            stream
+                .write_all(&[5, 0, 0, 1, 10, 0, 0, 1, 0x18, 0xeb])
+                .unwrap();
+            stream.write_all(b"+PONG\r\n").unwrap();
+        });
+
+        let proxy = proxy_config(ProxyKind::Socks5, port, Some("user"));
+        socks5_connect(&mut stream, &proxy, TARGET_HOST, TARGET_PORT).unwrap();
+        assert_pong(&mut stream);
+    }
+
+    #[test]
+    fn socks5_reports_rejected_authentication() {
+        let (mut stream, port) = stand_in(|mut stream| {
+            assert_eq!(read_bytes(&mut stream, 4), [5, 2, 0x00, 0x02]);
+            stream.write_all(&[5, 0x02]).unwrap();
+            assert_eq!(read_bytes(&mut stream, 13), b"\x01\x04user\x06secret");
+            stream.write_all(&[1, 1]).unwrap();
+        });
+
+        let proxy = proxy_config(ProxyKind::Socks5, port, Some("user"));
+        let result = socks5_connect(&mut stream, &proxy, TARGET_HOST, TARGET_PORT);
+        assert_eq!(result, Err("Proxy authentication failed".to_string()));
+    }
+
+    #[test]
+    fn socks5_consumes_a_domain_name_bound_address() {
+        let (mut stream, port) =
+            stand_in(|stream| socks5_no_auth(stream, b"\x05\x00\x00\x03\x0aproxy.host\x18\xeb"));
+
+        let proxy = proxy_config(ProxyKind::Socks5, port, None);
+        socks5_connect(&mut stream, &proxy, TARGET_HOST, TARGET_PORT).unwrap();
+        assert_pong(&mut stream);
+    }
+
+    #[test]
+    fn socks5_reports_the_reply_code() {
+        let (mut stream, port) =
+            stand_in(|stream| socks5_no_auth(stream, &[5, 5, 0, 1, 0, 0, 0, 0, 0, 0]));
+
+        let proxy = proxy_config(ProxyKind::Socks5, port, None);
+        let result = socks5_connect(&mut stream, &proxy, TARGET_HOST, TARGET_PORT);
+        assert_eq!(
+            result,
+            Err("Proxy could not reach the server: connection refused (5)".to_string())
+        );
+    }
+
+    #[test]
+    fn http_connects_with_authentication() {
+        let (mut stream, port) = stand_in(|mut stream| {
+            let header = read_http_header(&mut stream);
+            assert!(header.starts_with("CONNECT redis.internal:6379 HTTP/1.1\r\n"));
+            // "user:secret"
+            assert!(header.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n+PONG\r\n")
+                .unwrap();
+        });
+
+        let proxy = proxy_config(ProxyKind::Http, port, Some("user"));
+        http_connect(&mut stream, &proxy, TARGET_HOST, TARGET_PORT).unwrap();
+        assert_pong(&mut stream);
+    }
+
+    #[test]
+    fn http_reports_rejected_authentication() {
+        let (mut stream, port) = stand_in(|mut stream| {
+            read_http_header(&mut stream);
+            stream
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .unwrap();
+        });
+
+        let proxy = proxy_config(ProxyKind::Http, port, Some("user"));
+        let result = http_connect(&mut stream, &proxy, TARGET_HOST, TARGET_PORT);
+        assert_eq!(result, Err("Proxy authentication failed".to_string()));
+    }
+
+    #[test]
+    fn http_reports_a_refused_connect() {
+        let (mut stream, port) = stand_in(|mut stream| {
+            read_http_header(&mut stream);
+            stream.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n").unwrap();
+        });
+
+        let proxy = proxy_config(ProxyKind::Http, port, None);
+        let result = http_connect(&mut stream, &proxy, TARGET_HOST, TARGET_PORT);
+        assert_eq!(
+            result,
+            Err("Proxy refused CONNECT: HTTP/1.1 403 Forbidden".to_string())
+        );
+    }
+
+    #[test]
+    fn tunnel_stops_when_dropped() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            socks5_no_auth(stream, &[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        });
+
+        let proxy = proxy_config(ProxyKind::Socks5, port, None);
+        let tunnel =
+            ProxyTunnel::open(&proxy, TARGET_HOST, TARGET_PORT, Duration::from_secs(5)).unwrap();
+        let mut local = TcpStream::connect(("127.0.0.1", tunnel.local_port())).unwrap();
+        local
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_pong(&mut local);
+
+        drop(tunnel);
+        // The worker closes the forwarded connections once it stops.
+        assert_eq!(local.read(&mut [0u8; 1]).unwrap(), 0);
+    }
+}
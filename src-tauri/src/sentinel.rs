@@ -0,0 +1,280 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use log::{info, warn};
+use redis::Connection;
+use tadis_database::models::{ConnectionConfig, SentinelConfig};
+use tauri::{AppHandle, Manager};
+
+use crate::client::build_client_for;
+use crate::health;
+use crate::AppState;
+
+const DEFAULT_SENTINEL_PORT: u16 = 26379;
+const SENTINEL_TIMEOUT: Duration = Duration::from_secs(5);
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Asks the sentinels where the configured master, or one of its replicas,
+/// currently lives. Sentinels are tried in order until one answers.
+pub fn resolve_target(config: &ConnectionConfig) -> Result<(String, u16), String> {
+    let sentinel = sentinel_config(config)?;
+    let mut last_error = String::new();
+
+    for node in &sentinel.nodes {
+        match connect_sentinel(config, sentinel, node)
+            .and_then(|mut connection| query_target(&mut connection, sentinel))
+        {
+            Ok(target) => return Ok(target),
+            Err(e) => {
+                warn!("Sentinel '{}' could not resolve '{}': {}", node, sentinel.master_name, e);
+                last_error = e;
+            }
+        }
+    }
+
+    Err(format!(
+        "No sentinel could resolve master '{}': {}",
+        sentinel.master_name, last_error
+    ))
+}
+
+/// Follows `+switch-master` announcements for a connected Sentinel
+/// connection and reconnects it to the new master after a failover.
+///
+/// The watcher stops shortly after it is dropped.
+pub struct SentinelWatcher {
+    shutdown: Arc<AtomicBool>,
+}
+
+impl SentinelWatcher {
+    pub fn spawn(app_handle: AppHandle, config: ConnectionConfig) -> Self {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        {
+            let shutdown = shutdown.clone();
+            thread::spawn(move || watch(app_handle, config, shutdown));
+        }
+        Self { shutdown }
+    }
+}
+
+impl Drop for SentinelWatcher {
+    fn drop(&mut self) {
+        // Not joined: the worker may be waiting on the `AppState` lock that
+        // the dropping caller is holding.
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+}
+
+fn watch(app_handle: AppHandle, config: ConnectionConfig, shutdown: Arc<AtomicBool>) {
+    let Ok(sentinel) = sentinel_config(&config) else {
+        return;
+    };
+
+    for node in sentinel.nodes.iter().cycle() {
+        if shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if let Err(e) = listen(&app_handle, &config, sentinel, node, &shutdown) {
+            warn!("Stopped watching sentinel '{}' for '{}': {}", node, config.name, e);
+            thread::sleep(WATCH_POLL_INTERVAL);
+        }
+    }
+}
+
+fn listen(
+    app_handle: &AppHandle,
+    config: &ConnectionConfig,
+    sentinel: &SentinelConfig,
+    node: &str,
+    shutdown: &AtomicBool,
+) -> Result<(), String> {
+    let mut connection = connect_sentinel(config, sentinel, node)?;
+    let mut pubsub = connection.as_pubsub();
+    pubsub
+        .subscribe("+switch-master")
+        .map_err(|e| format!("Failed to subscribe: {}", e))?;
+    pubsub
+        .set_read_timeout(Some(WATCH_POLL_INTERVAL))
+        .map_err(|e| format!("Failed to subscribe: {}", e))?;
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let message = match pubsub.get_message() {
+            Ok(message) => message,
+            Err(e) if e.is_timeout() => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+
+        // <master name> <old ip> <old port> <new ip> <new port>
+        let payload: String = message.get_payload().unwrap_or_default();
+        if payload.split_whitespace().next() != Some(sentinel.master_name.as_str()) {
+            continue;
+        }
+
+        info!("Sentinel reported failover for '{}': {}", config.name, payload);
+        if let Err(e) = reconnect(app_handle, config) {
+            warn!("Failed to reconnect '{}' after failover: {}", config.name, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reopens the connection, which asks the sentinels for the new master.
+fn reconnect(app_handle: &AppHandle, config: &ConnectionConfig) -> Result<(), String> {
+    let state = app_handle.state::<Mutex<AppState>>();
+    tauri::async_runtime::block_on(health::reconnect(&state, &config.name)).map(|_| ())
+}
+
+fn sentinel_config(config: &ConnectionConfig) -> Result<&SentinelConfig, String> {
+    config
+        .sentinel
+        .as_ref()
+        .filter(|sentinel| !sentinel.nodes.is_empty() && !sentinel.master_name.is_empty())
+        .ok_or(format!(
+            "Connection '{}' needs a master name and at least one sentinel",
+            config.name
+        ))
+}
+
+fn connect_sentinel(
+    config: &ConnectionConfig,
+    sentinel: &SentinelConfig,
+    node: &str,
+) -> Result<Connection, String> {
+    let (host, port) = parse_node(node)?;
+    // Sentinels are only queried and subscribed to, which RESP2 covers.
+    let sentinel_config = ConnectionConfig {
+        resp3: false,
+        ..config.clone()
+    };
+    let client = build_client_for(
+        &sentinel_config,
+        &host,
+        port,
+        sentinel.username.as_deref().unwrap_or(""),
+        sentinel.password.as_deref().unwrap_or(""),
+        // Sentinels do not support SELECT.
+        0,
+    )?;
+
+    client
+        .get_connection_with_timeout(SENTINEL_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to sentinel: {}", e))
+}
+
+fn query_target(
+    connection: &mut Connection,
+    sentinel: &SentinelConfig,
+) -> Result<(String, u16), String> {
+    let master: Option<(String, u16)> = redis::cmd("SENTINEL")
+        .arg("get-master-addr-by-name")
+        .arg(&sentinel.master_name)
+        .query(connection)
+        .map_err(|e| format!("Failed to query master address: {}", e))?;
+    let master = master.ok_or(format!("Unknown master '{}'", sentinel.master_name))?;
+
+    if !sentinel.read_from_replica {
+        return Ok(master);
+    }
+
+    let replicas: Vec<HashMap<String, String>> = redis::cmd("SENTINEL")
+        .arg("replicas")
+        .arg(&sentinel.master_name)
+        .query(connection)
+        .map_err(|e| format!("Failed to query replicas: {}", e))?;
+
+    replicas
+        .iter()
+        .filter(|replica| is_healthy_replica(replica))
+        .find_map(|replica| Some((replica.get("ip")?.clone(), replica.get("port")?.parse().ok()?)))
+        .ok_or(format!("Master '{}' has no healthy replicas", sentinel.master_name))
+}
+
+fn is_healthy_replica(replica: &HashMap<String, String>) -> bool {
+    let flags = replica.get("flags").map(String::as_str).unwrap_or_default();
+    let link_ok = replica.get("master-link-status").map(String::as_str) == Some("ok");
+
+    link_ok
+        && !flags
+            .split(',')
+            .any(|flag| matches!(flag, "s_down" | "o_down" | "disconnected"))
+}
+
+/// Splits `host:port`, defaulting to the standard sentinel port. IPv6
+/// addresses need brackets to carry a port, e.g. `[::1]:26379`. The brackets
+/// are dropped from the returned host.
+fn parse_node(node: &str) -> Result<(String, u16), String> {
+    let node = node.trim();
+    let invalid = || format!("Invalid sentinel address '{}'", node);
+
+    if let Some(rest) = node.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+        let port = match rest.strip_prefix(':') {
+            Some(port) => port.parse().map_err(|_| invalid())?,
+            None if rest.is_empty() => DEFAULT_SENTINEL_PORT,
+            None => return Err(invalid()),
+        };
+        return Ok((host.to_string(), port));
+    }
+
+    match node.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => {
+            let port = port.parse().map_err(|_| invalid())?;
+            Ok((host.to_string(), port))
+        }
+        _ => Ok((node.to_string(), DEFAULT_SENTINEL_PORT)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_host_and_port() {
+        assert_eq!(
+            parse_node("10.0.0.1:26380"),
+            Ok(("10.0.0.1".to_string(), 26380))
+        );
+    }
+
+    #[test]
+    fn defaults_the_port_of_a_bare_hostname() {
+        assert_eq!(
+            parse_node(" sentinel.internal "),
+            Ok(("sentinel.internal".to_string(), DEFAULT_SENTINEL_PORT))
+        );
+    }
+
+    #[test]
+    fn strips_the_brackets_of_ipv6_addresses() {
+        assert_eq!(parse_node("[::1]:26380"), Ok(("::1".to_string(), 26380)));
+        assert_eq!(
+            parse_node("[::1]"),
+            Ok(("::1".to_string(), DEFAULT_SENTINEL_PORT))
+        );
+    }
+
+    #[test]
+    fn takes_unbracketed_ipv6_addresses_whole() {
+        assert_eq!(
+            parse_node("fe80::1"),
+            Ok(("fe80::1".to_string(), DEFAULT_SENTINEL_PORT))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_ports() {
+        assert!(parse_node("sentinel.internal:port").is_err());
+        assert!(parse_node("[::1]26379").is_err());
+        assert!(parse_node("[::1").is_err());
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    env, fs,
+    io::{self, ErrorKind, Read, Write},
+    net::{self, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use log::{info, warn};
+use mio::{
+    net::{TcpListener, TcpStream},
+    Events, Interest, Poll, Token, Waker,
+};
+use ssh2::{Channel, CheckResult, ErrorCode, HashType, KnownHostFileKind, Session};
+use tadis_database::models::SshConfig;
+
+use crate::proxy::ProxyTunnel;
+
+const SSH_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait before retrying a channel open or a write libssh2 could
+/// not complete yet.
+const RETRY_INTERVAL: Duration = Duration::from_millis(5);
+const LIBSSH2_ERROR_EAGAIN: i32 = -37;
+
+const LISTENER: Token = Token(0);
+const SESSION: Token = Token(1);
+const PIPE: Token = Token(2);
+const WAKE: Token = Token(3);
+
+/// A local port the client connects to instead of a server it cannot reach
+/// directly.
+pub enum Tunnel {
+    Ssh(SshTunnel),
+    Proxy(ProxyTunnel),
+}
+
+impl Tunnel {
+    pub fn local_port(&self) -> u16 {
+        match self {
+            Tunnel::Ssh(tunnel) => tunnel.local_port(),
+            Tunnel::Proxy(tunnel) => tunnel.local_port(),
+        }
+    }
+}
+
+/// A local port forward to `remote_host:remote_port` through an SSH host.
+///
+/// The forward stays open until the tunnel is dropped.
+pub struct SshTunnel {
+    local_port: u16,
+    shutdown: Arc<AtomicBool>,
+    waker: Arc<Waker>,
+}
+
+impl SshTunnel {
+    pub fn open(ssh: &SshConfig, remote_host: &str, remote_port: u16) -> Result<Self, String> {
+        let (session, socket) = open_session(ssh)?;
+
+        let listener = net::TcpListener::bind(("127.0.0.1", 0))
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .map_err(|e| format!("Failed to bind local tunnel port: {}", e))?;
+        let local_port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to bind local tunnel port: {}", e))?
+            .port();
+
+        // Channels share the session, so all forwarding happens on one thread
+        // with the session switched to non-blocking mode. It sleeps until the
+        // listener, the SSH socket or a forwarded connection has data.
+        session.set_blocking(false);
+        let mut listener = TcpListener::from_std(listener);
+        let mut socket = TcpStream::from_std(socket);
+        let poll = Poll::new()
+            .and_then(|poll| {
+                let registry = poll.registry();
+                registry.register(&mut listener, LISTENER, Interest::READABLE)?;
+                registry.register(&mut socket, SESSION, Interest::READABLE)?;
+                Ok(poll)
+            })
+            .map_err(|e| format!("Failed to set up SSH tunnel: {}", e))?;
+        let waker = Waker::new(poll.registry(), WAKE)
+            .map(Arc::new)
+            .map_err(|e| format!("Failed to set up SSH tunnel: {}", e))?;
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        {
+            let worker = Worker {
+                poll,
+                listener,
+                session,
+                _socket: socket,
+                _waker: waker.clone(),
+                remote_host: remote_host.to_string(),
+                remote_port,
+                shutdown: shutdown.clone(),
+            };
+            thread::spawn(move || worker.run());
+        }
+
+        info!(
+            "SSH tunnel via {}:{} listening on 127.0.0.1:{}",
+            ssh.host, ssh.port, local_port
+        );
+
+        Ok(Self {
+            local_port,
+            shutdown,
+            waker,
+        })
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+}
+
+impl Drop for SshTunnel {
+    /// Tells the worker to stop without waiting for it, as tunnels are dropped
+    /// while the app state is locked.
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Err(e) = self.waker.wake() {
+            warn!("Failed to stop SSH tunnel: {}", e);
+        }
+        info!("SSH tunnel on 127.0.0.1:{} closed", self.local_port);
+    }
+}
+
+/// Opens an authenticated session, and returns it with a handle to its socket
+/// to wait on.
+fn open_session(ssh: &SshConfig) -> Result<(Session, net::TcpStream), String> {
+    let address = (ssh.host.as_str(), ssh.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve SSH host '{}': {}", ssh.host, e))?
+        .next()
+        .ok_or(format!("Failed to resolve SSH host '{}'", ssh.host))?;
+
+    let stream = net::TcpStream::connect_timeout(&address, SSH_CONNECT_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to SSH host '{}': {}", ssh.host, e))?;
+    let socket = stream
+        .try_clone()
+        .map_err(|e| format!("Failed to connect to SSH host '{}': {}", ssh.host, e))?;
+
+    let mut session = Session::new().map_err(|e| format!("Failed to create SSH session: {}", e))?;
+    session.set_timeout(SSH_CONNECT_TIMEOUT.as_millis() as u32);
+    session.set_tcp_stream(stream);
+    session
+        .handshake()
+        .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    verify_host_key(&session, ssh)?;
+
+    let private_key_path = ssh
+        .private_key_path
+        .as_deref()
+        .filter(|path| !path.trim().is_empty());
+
+    match private_key_path {
+        Some(path) => session.userauth_pubkey_file(
+            &ssh.username,
+            None,
+            Path::new(path),
+            ssh.passphrase.as_deref().filter(|p| !p.is_empty()),
+        ),
+        None => session.userauth_password(&ssh.username, ssh.password.as_deref().unwrap_or("")),
+    }
+    .map_err(|e| format!("SSH authentication failed: {}", e))?;
+
+    if !session.authenticated() {
+        return Err("SSH authentication failed".to_string());
+    }
+
+    // Blocking calls must not time out once the tunnel is idle.
+    session.set_timeout(0);
+
+    Ok((session, socket))
+}
+
+/// Checks the key the SSH host presented, before any credentials are sent to
+/// it, against the configured fingerprint or else `~/.ssh/known_hosts`.
+fn verify_host_key(session: &Session, ssh: &SshConfig) -> Result<(), String> {
+    let (key, _) = session
+        .host_key()
+        .ok_or("SSH host presented no host key".to_string())?;
+    let hash = session
+        .host_key_hash(HashType::Sha256)
+        .ok_or("Failed to hash the SSH host key".to_string())?;
+    let fingerprint = STANDARD_NO_PAD.encode(hash);
+
+    let expected = ssh
+        .host_key_fingerprint
+        .as_deref()
+        .map(str::trim)
+        .filter(|f| !f.is_empty());
+    if let Some(expected) = expected {
+        let expected = expected.strip_prefix("SHA256:").unwrap_or(expected);
+        return if expected.trim_end_matches('=') == fingerprint {
+            Ok(())
+        } else {
+            Err(format!(
+                "SSH host key of '{}' is SHA256:{}, not the configured fingerprint. The connection may be intercepted",
+                ssh.host, fingerprint
+            ))
+        };
+    }
+
+    let mut known_hosts = session
+        .known_hosts()
+        .map_err(|e| format!("Failed to check SSH host key: {}", e))?;
+    if let Some(contents) = known_hosts_path().and_then(|path| fs::read_to_string(path).ok()) {
+        // libssh2 cannot parse every entry OpenSSH writes, such as certificate
+        // authorities, so lines are read one by one and those are skipped.
+        for line in contents.lines() {
+            let _ = known_hosts.read_str(line, KnownHostFileKind::OpenSSH);
+        }
+    }
+
+    match known_hosts.check_port(&ssh.host, ssh.port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(format!(
+            "SSH host key of '{}' (SHA256:{}) does not match ~/.ssh/known_hosts. The connection may be intercepted",
+            ssh.host, fingerprint
+        )),
+        CheckResult::NotFound => Err(format!(
+            "SSH host '{}' is not in ~/.ssh/known_hosts. Connect to it once with ssh, or set its host key fingerprint SHA256:{} after checking it",
+            ssh.host, fingerprint
+        )),
+        CheckResult::Failure => Err(format!(
+            "Failed to check SSH host key of '{}' against ~/.ssh/known_hosts",
+            ssh.host
+        )),
+    }
+}
+
+fn known_hosts_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".ssh").join("known_hosts"))
+}
+
+/// The forwarding thread of an `SshTunnel`.
+struct Worker {
+    poll: Poll,
+    listener: TcpListener,
+    session: Session,
+    /// Registered with `poll`, kept so it stays registered.
+    _socket: TcpStream,
+    /// Kept until the thread exits, so a wake-up is not lost with it.
+    _waker: Arc<Waker>,
+    remote_host: String,
+    remote_port: u16,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Worker {
+    fn run(mut self) {
+        let mut events = Events::with_capacity(64);
+        let mut pipes: Vec<Pipe> = Vec::new();
+        let mut buffer = [0u8; 16 * 1024];
+
+        // Readiness is only reported when it changes, so every wake-up drains
+        // the listener and then moves data until nothing is left to move.
+        while !self.shutdown.load(Ordering::Relaxed) {
+            if let Err(e) = self.accept(&mut pipes) {
+                warn!("SSH tunnel listener failed: {}", e);
+                break;
+            }
+
+            loop {
+                let mut moved = false;
+                pipes.retain_mut(|pipe| match pipe.pump(&mut buffer) {
+                    Ok(pumped) => {
+                        moved |= pumped;
+                        true
+                    }
+                    Err(_) => false,
+                });
+                if !moved {
+                    break;
+                }
+            }
+
+            match self.poll.poll(&mut events, None) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    warn!("SSH tunnel failed: {}", e);
+                    break;
+                }
+            }
+        }
+
+        drop(pipes);
+        let _ = self.session.disconnect(None, "Tunnel closed", None);
+    }
+
+    /// Forwards every pending local connection over a new channel.
+    fn accept(&self, pipes: &mut Vec<Pipe>) -> io::Result<()> {
+        loop {
+            let mut stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            match open_channel(
+                &self.session,
+                &self.remote_host,
+                self.remote_port,
+                &self.shutdown,
+            ) {
+                Ok(channel) => {
+                    match self
+                        .poll
+                        .registry()
+                        .register(&mut stream, PIPE, Interest::READABLE)
+                    {
+                        Ok(()) => pipes.push(Pipe { stream, channel }),
+                        Err(e) => warn!("Failed to prepare tunnel connection: {}", e),
+                    }
+                }
+                Err(e) => warn!(
+                    "Failed to open SSH channel to {}:{}: {}",
+                    self.remote_host, self.remote_port, e
+                ),
+            }
+        }
+    }
+}
+
+fn open_channel(
+    session: &Session,
+    remote_host: &str,
+    remote_port: u16,
+    shutdown: &AtomicBool,
+) -> Result<Channel, ssh2::Error> {
+    loop {
+        match session.channel_direct_tcpip(remote_host, remote_port, None) {
+            Err(e) if e.code() == ErrorCode::Session(LIBSSH2_ERROR_EAGAIN) => {
+                if shutdown.load(Ordering::Relaxed) {
+                    return Err(e);
+                }
+                thread::sleep(RETRY_INTERVAL);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// One accepted local connection and the SSH channel it is forwarded to.
+struct Pipe {
+    stream: TcpStream,
+    channel: Channel,
+}
+
+impl Pipe {
+    /// Moves whatever is ready in either direction. Returns whether any bytes
+    /// were moved, or an error once either side has closed.
+    fn pump(&mut self, buffer: &mut [u8]) -> io::Result<bool> {
+        let mut moved = false;
+
+        match self.stream.read(buffer) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(n) => {
+                write_all(&mut self.channel, &buffer[..n])?;
+                moved = true;
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+
+        match self.channel.read(buffer) {
+            Ok(0) if self.channel.eof() => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(0) => {}
+            Ok(n) => {
+                write_all(&mut self.stream, &buffer[..n])?;
+                moved = true;
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(moved)
+    }
+}
+
+/// `Write::write_all` for non-blocking writers.
+fn write_all(writer: &mut impl Write, mut data: &[u8]) -> io::Result<()> {
+    while !data.is_empty() {
+        match writer.write(data) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => data = &data[n..],
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(RETRY_INTERVAL),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,452 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::models::ConnectionConfig;
+
+/// Versions the ciphertext format. Whether a value is encrypted at all is
+/// tracked apart from it, as a plain text secret may start with it too.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+/// Encrypted under a passphrase key so a wrong passphrase can be told apart.
+const VERIFIER: &str = "tadis";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+/// Contents of the key file next to `db.sqlite`. It holds either the key
+/// itself or, with a master password, only what is needed to re-derive it.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum KeyFile {
+    File {
+        key: String,
+    },
+    MasterPassword(PassphraseKey),
+    /// Stands in for a key file that could not be loaded, which keeps the
+    /// credentials locked.
+    #[serde(skip)]
+    Unreadable {
+        error: String,
+    },
+}
+
+/// What is needed to re-derive a key from a passphrase and check that the
+/// passphrase is right. Safe to store next to the secrets it protects.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PassphraseKey {
+    salt: String,
+    verifier: String,
+}
+
+impl PassphraseKey {
+    /// Derives a new key from `passphrase` with a random salt.
+    pub fn create(passphrase: &str) -> Result<(Self, Cipher), String> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let cipher = Cipher::derive(passphrase, &salt)?;
+        let key = Self {
+            salt: STANDARD.encode(salt),
+            verifier: cipher.encrypt(VERIFIER)?,
+        };
+
+        Ok((key, cipher))
+    }
+
+    /// Re-derives the key, failing if `passphrase` is not the one it was
+    /// created with.
+    pub fn unlock(&self, passphrase: &str) -> Result<Cipher, String> {
+        let salt = STANDARD
+            .decode(&self.salt)
+            .map_err(|e| format!("Failed to decode key salt: {}", e))?;
+        let cipher = Cipher::derive(passphrase, &salt)?;
+        match cipher.decrypt(&self.verifier) {
+            Ok(value) if value == VERIFIER => Ok(cipher),
+            _ => Err("Incorrect password".to_string()),
+        }
+    }
+}
+
+/// Encrypts and decrypts individual secrets under one key.
+pub struct Cipher(Aes256Gcm);
+
+impl Cipher {
+    fn random() -> (Self, String) {
+        let key = Aes256Gcm::generate_key(OsRng);
+        (Self(Aes256Gcm::new(&key)), STANDARD.encode(key))
+    }
+
+    fn from_base64(key: &str) -> Result<Self, String> {
+        let key = STANDARD
+            .decode(key)
+            .map_err(|e| format!("Failed to decode credential key: {}", e))?;
+        Aes256Gcm::new_from_slice(&key)
+            .map(Self)
+            .map_err(|e| format!("Invalid credential key: {}", e))
+    }
+
+    fn derive(passphrase: &str, salt: &[u8]) -> Result<Self, String> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| format!("Failed to derive key from password: {}", e))?;
+
+        Ok(Self(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))))
+    }
+
+    /// Empty values stay empty, there is no secret to hide.
+    pub fn encrypt(&self, value: &str) -> Result<String, String> {
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .0
+            .encrypt(Nonce::from_slice(&nonce), value.as_bytes())
+            .map_err(|e| format!("Failed to encrypt secret: {}", e))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(payload)))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String, String> {
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+        let encoded = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or("Failed to decrypt secret: value is not encrypted")?;
+
+        let payload = STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("Failed to decode secret: {}", e))?;
+        if payload.len() < NONCE_LEN {
+            return Err("Failed to decrypt secret: value is truncated".to_string());
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt secret: wrong key or corrupted value".to_string())?;
+        String::from_utf8(plaintext).map_err(|e| format!("Failed to decrypt secret: {}", e))
+    }
+
+    pub fn encrypt_config(&self, config: &mut ConnectionConfig) -> Result<(), String> {
+        map_secrets(config, |value| self.encrypt(value))
+    }
+
+    pub fn decrypt_config(&self, config: &mut ConnectionConfig) -> Result<(), String> {
+        map_secrets(config, |value| self.decrypt(value))
+    }
+}
+
+/// Encrypts connection secrets at rest. Locked while a master password is set
+/// and has not been entered yet.
+pub struct Credentials {
+    key_path: PathBuf,
+    key_file: KeyFile,
+    cipher: Option<Cipher>,
+}
+
+impl Credentials {
+    /// Loads the key file, creating one with a random key on first start.
+    /// A missing key file is an error when `has_encrypted_secrets`, as a new
+    /// key could never decrypt them.
+    pub fn load(key_path: PathBuf, has_encrypted_secrets: bool) -> Result<Self, String> {
+        if !key_path.exists() {
+            if has_encrypted_secrets {
+                return Err(
+                    "The credential key file is missing, stored secrets cannot be decrypted without it"
+                        .to_string(),
+                );
+            }
+            let (_, key) = Cipher::random();
+            write_key_file(&key_path, &KeyFile::File { key })?;
+        }
+
+        let contents = fs::read_to_string(&key_path)
+            .map_err(|e| format!("Failed to read credential key file: {}", e))?;
+        let key_file: KeyFile = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse credential key file: {}", e))?;
+
+        let cipher = match &key_file {
+            KeyFile::File { key } => Some(Cipher::from_base64(key)?),
+            KeyFile::MasterPassword(_) | KeyFile::Unreadable { .. } => None,
+        };
+
+        Ok(Self {
+            key_path,
+            key_file,
+            cipher,
+        })
+    }
+
+    /// Locked credentials standing in for a key file that failed to `load`,
+    /// so the app can still start and report `error`.
+    pub fn unreadable(key_path: PathBuf, error: String) -> Self {
+        Self {
+            key_path,
+            key_file: KeyFile::Unreadable { error },
+            cipher: None,
+        }
+    }
+
+    /// Why the key file could not be loaded, if it could not.
+    pub fn load_error(&self) -> Option<&str> {
+        match &self.key_file {
+            KeyFile::Unreadable { error } => Some(error),
+            _ => None,
+        }
+    }
+
+    pub fn has_master_password(&self) -> bool {
+        matches!(self.key_file, KeyFile::MasterPassword(_))
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.cipher.is_none()
+    }
+
+    /// Derives the key from `master_password`, failing if it is not the one
+    /// the secrets were encrypted with.
+    pub fn unlock(&mut self, master_password: &str) -> Result<(), String> {
+        let key = match &self.key_file {
+            KeyFile::File { .. } => return Ok(()),
+            KeyFile::MasterPassword(key) => key,
+            KeyFile::Unreadable { error } => return Err(error.clone()),
+        };
+        let cipher = key
+            .unlock(master_password)
+            .map_err(|_| "Incorrect master password".to_string())?;
+        self.cipher = Some(cipher);
+
+        Ok(())
+    }
+
+    /// Fresh credentials keyed by `master_password`, or by a new random key
+    /// file when it is `None`. Nothing is written until `save`, so stored
+    /// secrets can be re-encrypted first.
+    pub fn with_master_password(&self, master_password: Option<&str>) -> Result<Self, String> {
+        let (key_file, cipher) = match master_password {
+            Some(master_password) => {
+                let (key, cipher) = PassphraseKey::create(master_password)?;
+                (KeyFile::MasterPassword(key), cipher)
+            }
+            None => {
+                let (cipher, key) = Cipher::random();
+                (KeyFile::File { key }, cipher)
+            }
+        };
+
+        Ok(Self {
+            key_path: self.key_path.clone(),
+            key_file,
+            cipher: Some(cipher),
+        })
+    }
+
+    /// Replaces the key file with the one for these credentials.
+    pub fn save(&self) -> Result<(), String> {
+        write_key_file(&self.key_path, &self.key_file)
+    }
+
+    /// Encrypts the secrets in `config` unless they already are.
+    pub fn encrypt_config(&self, config: &mut ConnectionConfig) -> Result<(), String> {
+        if !config.secrets_encrypted {
+            self.cipher()?.encrypt_config(config)?;
+            config.secrets_encrypted = true;
+        }
+        Ok(())
+    }
+
+    /// Decrypts the secrets in a stored config. Only call this right before
+    /// connecting or re-encrypting, the plain text must not travel back to
+    /// the UI.
+    pub fn decrypt_config(&self, config: &mut ConnectionConfig) -> Result<(), String> {
+        if config.secrets_encrypted {
+            self.cipher()?.decrypt_config(config)?;
+            config.secrets_encrypted = false;
+        }
+        Ok(())
+    }
+
+    /// Decrypts the secrets in `config` as the UI sent it back for `stored`.
+    /// Those it left as they were loaded are still the stored ciphertext and
+    /// are replaced by their plain text. The others were typed in.
+    pub fn decrypt_submitted(
+        &self,
+        config: &mut ConnectionConfig,
+        stored: Option<&ConnectionConfig>,
+    ) -> Result<(), String> {
+        let Some(stored) = stored else {
+            return Ok(());
+        };
+
+        let mut stored = stored.clone();
+        let mut plain = stored.clone();
+        self.decrypt_config(&mut plain)?;
+        for ((secret, stored), plain) in secrets(config)
+            .into_iter()
+            .zip(secrets(&mut stored))
+            .zip(secrets(&mut plain))
+        {
+            if let (Some(secret), Some(stored), Some(plain)) = (secret, stored, plain) {
+                if *secret == *stored {
+                    *secret = std::mem::take(plain);
+                }
+            }
+        }
+        config.secrets_encrypted = false;
+
+        Ok(())
+    }
+
+    fn cipher(&self) -> Result<&Cipher, String> {
+        match (&self.cipher, &self.key_file) {
+            (Some(cipher), _) => Ok(cipher),
+            (None, KeyFile::Unreadable { error }) => Err(error.clone()),
+            (None, _) => Err("Credentials are locked, enter the master password first".to_string()),
+        }
+    }
+}
+
+/// Applies `f` to every secret in `config`.
+fn map_secrets(
+    config: &mut ConnectionConfig,
+    f: impl Fn(&str) -> Result<String, String>,
+) -> Result<(), String> {
+    for secret in secrets(config).into_iter().flatten() {
+        *secret = f(secret)?;
+    }
+
+    Ok(())
+}
+
+/// The secrets in `config`, always in the same slots: the password and the
+/// SSH, sentinel and proxy credentials. Slots that are not set are `None`.
+fn secrets(config: &mut ConnectionConfig) -> [Option<&mut String>; 5] {
+    let (ssh_password, ssh_passphrase) = match config.ssh.as_mut() {
+        Some(ssh) => (ssh.password.as_mut(), ssh.passphrase.as_mut()),
+        None => (None, None),
+    };
+
+    [
+        Some(&mut config.password),
+        ssh_password,
+        ssh_passphrase,
+        config.sentinel.as_mut().and_then(|s| s.password.as_mut()),
+        config.proxy.as_mut().and_then(|p| p.password.as_mut()),
+    ]
+}
+
+/// Writes the key file through a temporary file that only the owner can read,
+/// renamed into place once it is on disk, so the key is never exposed and a
+/// crash never leaves a half-written key file behind.
+fn write_key_file(path: &Path, key_file: &KeyFile) -> Result<(), String> {
+    let contents = serde_json::to_string(key_file)
+        .map_err(|e| format!("Failed to serialize credential key file: {}", e))?;
+
+    let temp_path = path.with_extension("key.tmp");
+    // Left over from a crash, possibly with other permissions.
+    match fs::remove_file(&temp_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(format!("Failed to remove stale credential key file: {}", e));
+        }
+        _ => {}
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&temp_path)
+        .map_err(|e| format!("Failed to create credential key file: {}", e))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write credential key file: {}", e))?;
+
+    fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to replace credential key file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials() -> Credentials {
+        let (cipher, key) = Cipher::random();
+        Credentials {
+            key_path: PathBuf::new(),
+            key_file: KeyFile::File { key },
+            cipher: Some(cipher),
+        }
+    }
+
+    #[test]
+    fn encrypts_secrets_that_look_encrypted() {
+        let (cipher, _) = Cipher::random();
+        let encrypted = cipher.encrypt("enc:v1:secret").unwrap();
+
+        assert_ne!(encrypted, "enc:v1:secret");
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "enc:v1:secret");
+    }
+
+    #[test]
+    fn rejects_plain_text_where_ciphertext_is_expected() {
+        let (cipher, _) = Cipher::random();
+
+        assert!(cipher.decrypt("secret").is_err());
+        assert_eq!(cipher.decrypt("").unwrap(), "");
+    }
+
+    #[test]
+    fn decrypts_only_the_secrets_submitted_unchanged() {
+        let credentials = credentials();
+        let mut stored = ConnectionConfig {
+            password: "stored".to_string(),
+            proxy: Some(Default::default()),
+            ..Default::default()
+        };
+        stored.proxy.as_mut().unwrap().password = Some("proxy".to_string());
+        credentials.encrypt_config(&mut stored).unwrap();
+
+        let mut submitted = stored.clone();
+        submitted.secrets_encrypted = false;
+        submitted.proxy.as_mut().unwrap().password = Some("enc:v1:typed".to_string());
+        credentials
+            .decrypt_submitted(&mut submitted, Some(&stored))
+            .unwrap();
+
+        assert_eq!(submitted.password, "stored");
+        assert_eq!(
+            submitted.proxy.unwrap().password.as_deref(),
+            Some("enc:v1:typed")
+        );
+    }
+
+    #[test]
+    fn does_not_replace_a_missing_key_that_secrets_need() {
+        let key_path = std::env::temp_dir()
+            .join("tadis-missing-key")
+            .join("credentials.key");
+
+        assert!(Credentials::load(key_path.clone(), true).is_err());
+        assert!(!key_path.exists());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod crypto;
 pub mod models;
 pub mod queries;
 pub mod plugin;
@@ -1,13 +1,374 @@
+use std::{fmt, time::Duration};
+
 use serde::{Serialize, Deserialize};
 
+/// Stands in for secrets in `Debug` output so configs are safe to log.
+const REDACTED: &str = "<redacted>";
+
+const DEFAULT_CONNECT_TIMEOUT_SECS: u32 = 10;
+const DEFAULT_KEEPALIVE_SECS: u32 = 15;
+const DEFAULT_CLIENT_NAME: &str = "tadis";
+const DEFAULT_KEY_DELIMITER: &str = ":";
+
+fn redact(secret: &str) -> &str {
+    if secret.is_empty() {
+        secret
+    } else {
+        REDACTED
+    }
+}
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 #[serde(default, rename_all = "camelCase")]
 pub struct ConnectionConfig {
+    /// Stable key assigned when the connection is first saved, so the name can
+    /// change. `None` until then.
+    pub id: Option<i64>,
     pub name: String,
     pub host: String,
     pub port: u16,
     pub username: String,
     pub password: String,
+    /// Logical database selected after connecting. Clusters only have database 0.
+    pub database: i64,
+    /// Connect with `rediss://` instead of plain `redis://`.
+    pub tls_enabled: bool,
+    /// PEM bundle used to verify the server instead of the system trust store.
+    pub tls_ca_cert_path: Option<String>,
+    /// PEM client certificate for mutual TLS, must be set together with the key.
+    pub tls_client_cert_path: Option<String>,
+    /// PEM private key matching `tls_client_cert_path`.
+    pub tls_client_key_path: Option<String>,
+    /// Accept a server certificate issued for another name. The chain is
+    /// still verified against the trusted CAs. Standalone connections only.
+    pub tls_skip_verify: bool,
+    /// Name sent with SNI and checked against the server certificate instead
+    /// of `host`. Standalone connections only.
+    pub tls_server_name: Option<String>,
+    /// Reach the server through a local port forward over this SSH host.
+    pub ssh: Option<SshConfig>,
+    /// SOCKS5 or HTTP proxy the TCP connection is opened through.
+    pub proxy: Option<ProxyConfig>,
+    /// In `Cluster` mode `host` and `port` name the seed node used to discover the cluster.
+    pub mode: ConnectionMode,
+    /// Used instead of `host` and `port` when `mode` is `Sentinel`.
+    pub sentinel: Option<SentinelConfig>,
+    /// Used instead of `host` and `port` when `mode` is `Unix`.
+    pub socket_path: Option<String>,
+    /// Negotiate RESP3 with `HELLO 3` instead of speaking RESP2. Needs Redis 6.
+    /// Map, set and double replies are read into the same hash, set and
+    /// sorted set values as their RESP2 forms. Push replies are not shown, as
+    /// the app neither subscribes nor tracks keys over these connections.
+    pub resp3: bool,
+    /// Unset or 0 uses the default of 10 seconds.
+    pub connect_timeout_secs: Option<u32>,
+    /// Read/write timeout for every command. Unset or 0 waits indefinitely.
+    pub response_timeout_secs: Option<u32>,
+    /// Seconds between the PINGs that keep an idle connection from being
+    /// dropped and notice when it was. Unset or 0 uses the default of 15.
+    pub keepalive_secs: Option<u32>,
+    /// Sent with `CLIENT SETNAME` so the app shows up in `CLIENT LIST`.
+    pub client_name: Option<String>,
+    /// Folder the connection is listed under. Ungrouped when unset.
+    pub group: Option<String>,
+    pub tags: Vec<String>,
+    /// Label colour telling environments apart at a glance, as a CSS colour.
+    pub color: Option<String>,
+    /// Position in the connection list, lowest first. Ties sort by name.
+    pub sort_order: i64,
+    /// Rejects every command that writes, and reads from replicas in a cluster.
+    pub read_only: bool,
+    pub environment: Environment,
+    /// Separators splitting keys into the folders of the key tree, any of
+    /// them at each level. Empty uses `:`.
+    pub key_delimiters: Vec<String>,
+    /// Lists keys without folders.
+    pub flat_key_list: bool,
+    /// Whether the secrets were stored encrypted. Only the database sets it,
+    /// secrets sent by the UI or read from an import are plain text unless
+    /// they are the ones it loaded.
+    #[serde(skip)]
+    pub secrets_encrypted: bool,
+    /// UTC timestamps kept by the database, ignored when saving.
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    /// Set while the connection is in the trash.
+    pub deleted_at: Option<String>,
+    pub last_connected_at: Option<String>,
+}
+
+impl ConnectionConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        seconds(self.connect_timeout_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS.into()))
+    }
+
+    pub fn response_timeout(&self) -> Option<Duration> {
+        seconds(self.response_timeout_secs)
+    }
+
+    pub fn keepalive_interval(&self) -> Duration {
+        seconds(self.keepalive_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_KEEPALIVE_SECS.into()))
+    }
+
+    pub fn client_name(&self) -> &str {
+        self.client_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_CLIENT_NAME)
+    }
+
+    /// The separators the key tree is built with, none for a flat list.
+    pub fn key_delimiters(&self) -> Vec<String> {
+        if self.flat_key_list {
+            return Vec::new();
+        }
+
+        let delimiters: Vec<String> = self
+            .key_delimiters
+            .iter()
+            .filter(|delimiter| !delimiter.is_empty())
+            .cloned()
+            .collect();
+        if delimiters.is_empty() {
+            vec![DEFAULT_KEY_DELIMITER.to_string()]
+        } else {
+            delimiters
+        }
+    }
+}
+
+fn seconds(value: Option<u32>) -> Option<Duration> {
+    value
+        .filter(|secs| *secs > 0)
+        .map(|secs| Duration::from_secs(secs.into()))
+}
+
+impl fmt::Debug for ConnectionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionConfig")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &redact(&self.password))
+            .field("database", &self.database)
+            .field("tls_enabled", &self.tls_enabled)
+            .field("tls_ca_cert_path", &self.tls_ca_cert_path)
+            .field("tls_client_cert_path", &self.tls_client_cert_path)
+            .field("tls_client_key_path", &self.tls_client_key_path)
+            .field("tls_skip_verify", &self.tls_skip_verify)
+            .field("tls_server_name", &self.tls_server_name)
+            .field("ssh", &self.ssh)
+            .field("proxy", &self.proxy)
+            .field("mode", &self.mode)
+            .field("sentinel", &self.sentinel)
+            .field("socket_path", &self.socket_path)
+            .field("resp3", &self.resp3)
+            .field("connect_timeout_secs", &self.connect_timeout_secs)
+            .field("response_timeout_secs", &self.response_timeout_secs)
+            .field("keepalive_secs", &self.keepalive_secs)
+            .field("client_name", &self.client_name)
+            .field("group", &self.group)
+            .field("tags", &self.tags)
+            .field("color", &self.color)
+            .field("sort_order", &self.sort_order)
+            .field("read_only", &self.read_only)
+            .field("environment", &self.environment)
+            .field("key_delimiters", &self.key_delimiters)
+            .field("flat_key_list", &self.flat_key_list)
+            .field("secrets_encrypted", &self.secrets_encrypted)
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .field("deleted_at", &self.deleted_at)
+            .field("last_connected_at", &self.last_connected_at)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionMode {
+    #[default]
+    Standalone,
+    Sentinel,
+    Cluster,
+    /// A local server reached through its unix domain socket.
+    Unix,
+}
+
+impl ConnectionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionMode::Standalone => "standalone",
+            ConnectionMode::Sentinel => "sentinel",
+            ConnectionMode::Cluster => "cluster",
+            ConnectionMode::Unix => "unix",
+        }
+    }
+}
+
+impl From<&str> for ConnectionMode {
+    fn from(value: &str) -> Self {
+        match value {
+            "sentinel" => ConnectionMode::Sentinel,
+            "cluster" => ConnectionMode::Cluster,
+            "unix" => ConnectionMode::Unix,
+            _ => ConnectionMode::Standalone,
+        }
+    }
+}
+
+/// How careful to be with a connection. Shown next to it so production stands
+/// out.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Environment {
+    #[default]
+    Dev,
+    Staging,
+    Prod,
+}
+
+impl Environment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Environment::Dev => "dev",
+            Environment::Staging => "staging",
+            Environment::Prod => "prod",
+        }
+    }
+}
+
+impl From<&str> for Environment {
+    fn from(value: &str) -> Self {
+        match value {
+            "staging" => Environment::Staging,
+            "prod" => Environment::Prod,
+            _ => Environment::Dev,
+        }
+    }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProxyKind {
+    /// Resolves the Redis host on the proxy, like `socks5h://`.
+    #[default]
+    Socks5,
+    /// An HTTP proxy that allows `CONNECT` to the Redis port.
+    Http,
+}
+
+impl ProxyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyKind::Socks5 => "socks5",
+            ProxyKind::Http => "http",
+        }
+    }
+}
+
+impl From<&str> for ProxyKind {
+    fn from(value: &str) -> Self {
+        match value {
+            "http" => ProxyKind::Http,
+            _ => ProxyKind::Socks5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    /// Proxy credentials, sent only when a username is set.
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("kind", &self.kind)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password.as_deref().map(redact))
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SshConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    /// Used when no private key is configured.
+    pub password: Option<String>,
+    pub private_key_path: Option<String>,
+    pub passphrase: Option<String>,
+    /// SHA256 fingerprint the host key must have, as printed by
+    /// `ssh-keygen -l`. Unset checks the host against `~/.ssh/known_hosts`.
+    pub host_key_fingerprint: Option<String>,
+}
+
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 22,
+            username: String::new(),
+            password: None,
+            private_key_path: None,
+            passphrase: None,
+            host_key_fingerprint: None,
+        }
+    }
+}
+
+impl fmt::Debug for SshConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SshConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password.as_deref().map(redact))
+            .field("private_key_path", &self.private_key_path)
+            .field("passphrase", &self.passphrase.as_deref().map(redact))
+            .field("host_key_fingerprint", &self.host_key_fingerprint)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SentinelConfig {
+    pub master_name: String,
+    /// Sentinel addresses as `host:port`.
+    pub nodes: Vec<String>,
+    /// Credentials for the sentinels themselves, which often differ from the data nodes.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Browse one of the master's replicas instead of the master.
+    pub read_from_replica: bool,
+}
+
+impl fmt::Debug for SentinelConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SentinelConfig")
+            .field("master_name", &self.master_name)
+            .field("nodes", &self.nodes)
+            .field("username", &self.username)
+            .field("password", &self.password.as_deref().map(redact))
+            .field("read_from_replica", &self.read_from_replica)
+            .finish()
+    }
+}
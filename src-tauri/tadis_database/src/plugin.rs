@@ -5,9 +5,14 @@ use std::{
     sync::Mutex,
 };
 
-use log::info;
+use log::{error, info, warn};
 use sqlx::{migrate::Migrator, sqlite::SqliteConnectOptions, SqlitePool};
+use crate::{
+    crypto::Credentials,
+    queries::{encrypt_stored_secrets, has_encrypted_secrets},
+};
 use tauri::{
+    async_runtime::Mutex as AsyncMutex,
     path::BaseDirectory,
     plugin::{self, TauriPlugin},
     AppHandle, Manager, Runtime,
@@ -54,6 +59,29 @@ impl Builder {
 
                 app.manage(Mutex::new(pool));
 
+                let key_path = app_data_dir.join("credentials.key");
+                // A lost key file must not be replaced while secrets need it,
+                // so they are assumed to when this cannot be checked.
+                let encrypted = tauri::async_runtime::block_on(has_encrypted_secrets(app))
+                    .unwrap_or_else(|e| {
+                        error!("Failed to check for encrypted secrets: {}", e);
+                        true
+                    });
+                let credentials =
+                    Credentials::load(key_path.clone(), encrypted).unwrap_or_else(|e| {
+                        error!("Failed to load credential key: {}", e);
+                        Credentials::unreadable(key_path, e)
+                    });
+                let locked = credentials.is_locked();
+                app.manage(AsyncMutex::new(credentials));
+
+                // Behind a master password this happens on unlock instead.
+                if !locked {
+                    if let Err(e) = tauri::async_runtime::block_on(encrypt_stored_secrets(app)) {
+                        warn!("Failed to encrypt stored secrets: {}", e);
+                    }
+                }
+
                 Ok(())
             })
             .build()
@@ -1,12 +1,94 @@
 use std::sync::Mutex;
 
-use crate::models::ConnectionConfig;
+use crate::crypto::Credentials;
+use crate::models::{ConnectionConfig, ProxyConfig, SentinelConfig, SshConfig};
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteRow};
 use sqlx::Row;
-use sqlx::SqlitePool;
-use tauri::{Manager, Runtime};
+use sqlx::{Sqlite, SqlitePool};
+use tauri::{async_runtime::Mutex as AsyncMutex, Manager, Runtime};
 
+/// Every column besides `id` and `name`, in the order `bind_config_columns` binds them.
+const CONFIG_COLUMNS: &[&str] = &[
+    "host",
+    "port",
+    "username",
+    "password",
+    "database",
+    "tls_enabled",
+    "tls_ca_cert_path",
+    "tls_client_cert_path",
+    "tls_client_key_path",
+    "tls_skip_verify",
+    "tls_server_name",
+    "ssh_host",
+    "ssh_port",
+    "ssh_username",
+    "ssh_password",
+    "ssh_private_key_path",
+    "ssh_passphrase",
+    "ssh_host_key_fingerprint",
+    "mode",
+    "sentinel_master_name",
+    "sentinel_nodes",
+    "sentinel_username",
+    "sentinel_password",
+    "sentinel_read_from_replica",
+    "socket_path",
+    "resp3",
+    "connect_timeout_secs",
+    "response_timeout_secs",
+    "keepalive_secs",
+    "client_name",
+    "group_name",
+    "tags",
+    "color",
+    "sort_order",
+    "read_only",
+    "environment",
+    "key_delimiters",
+    "flat_key_list",
+    "proxy_kind",
+    "proxy_host",
+    "proxy_port",
+    "proxy_username",
+    "proxy_password",
+    "secrets_encrypted",
+];
+
+/// Timestamps the database maintains. Read but never bound.
+const METADATA_COLUMNS: &[&str] = &[
+    "created_at",
+    "updated_at",
+    "deleted_at",
+    "last_connected_at",
+];
+
+/// The connections that are not in the trash.
 pub async fn get_all_connection_configurations<R: Runtime>(
     mgr: &impl Manager<R>,
+) -> Result<Vec<ConnectionConfig>, sqlx::Error> {
+    select_connection_configurations(mgr, "deleted_at IS NULL").await
+}
+
+/// The connections in the trash, most recently deleted first.
+pub async fn get_deleted_connection_configurations<R: Runtime>(
+    mgr: &impl Manager<R>,
+) -> Result<Vec<ConnectionConfig>, sqlx::Error> {
+    select_connection_configurations(mgr, "deleted_at IS NOT NULL").await
+}
+
+/// Every stored connection, including those in the trash, whose secrets must
+/// be kept under the current key as well.
+pub async fn get_stored_connection_configurations<R: Runtime>(
+    mgr: &impl Manager<R>,
+) -> Result<Vec<ConnectionConfig>, sqlx::Error> {
+    select_connection_configurations(mgr, "1 = 1").await
+}
+
+async fn select_connection_configurations<R: Runtime>(
+    mgr: &impl Manager<R>,
+    condition: &str,
 ) -> Result<Vec<ConnectionConfig>, sqlx::Error> {
     let pool = {
         let state = mgr.state::<Mutex<SqlitePool>>();
@@ -14,24 +96,52 @@ pub async fn get_all_connection_configurations<R: Runtime>(
         pool.clone()
     };
 
-    let rows = sqlx::query("SELECT name, host, port, username, password FROM connection_configs")
-        .fetch_all(&pool)
-        .await?;
+    let sql = format!(
+        "{} WHERE {} ORDER BY deleted_at DESC, sort_order, name COLLATE NOCASE",
+        select_sql(),
+        condition
+    );
+    let rows = sqlx::query(&sql).fetch_all(&pool).await?;
 
-    let configs: Vec<ConnectionConfig> = rows
-        .iter()
-        .map(|row| ConnectionConfig {
-            name: row.get("name"),
-            host: row.get("host"),
-            port: row.get("port"),
-            username: row.get("username"),
-            password: row.get("password"),
-        })
-        .collect();
+    let configs: Vec<ConnectionConfig> = rows.iter().map(connection_config_from_row).collect();
 
     Ok(configs)
 }
 
+/// Looks up the connection named `name` outside the trash. Those in it may
+/// share a name.
+pub async fn get_connection_configuration<R: Runtime>(
+    mgr: &impl Manager<R>,
+    name: String,
+) -> Result<Option<ConnectionConfig>, sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    let sql = format!("{} WHERE name = ? AND deleted_at IS NULL", select_sql());
+    let row = sqlx::query(&sql).bind(name).fetch_optional(&pool).await?;
+
+    Ok(row.as_ref().map(connection_config_from_row))
+}
+
+pub async fn get_connection_configuration_by_id<R: Runtime>(
+    mgr: &impl Manager<R>,
+    id: i64,
+) -> Result<Option<ConnectionConfig>, sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    let sql = format!("{} WHERE id = ?", select_sql());
+    let row = sqlx::query(&sql).bind(id).fetch_optional(&pool).await?;
+
+    Ok(row.as_ref().map(connection_config_from_row))
+}
+
 pub async fn connection_name_exists<R: Runtime>(
     mgr: &impl Manager<R>,
     name: String,
@@ -42,37 +152,63 @@ pub async fn connection_name_exists<R: Runtime>(
         pool.clone()
     };
 
-    let count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM connection_configs WHERE name = ?")
-        .bind(name)
-        .fetch_one(&pool)
-        .await?;
+    let count: i32 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM connection_configs WHERE name = ? AND deleted_at IS NULL",
+    )
+    .bind(name)
+    .fetch_one(&pool)
+    .await?;
 
     Ok(count > 0)
 }
 
+/// Whether any stored connection has a secret encrypted under the credential
+/// key.
+pub async fn has_encrypted_secrets<R: Runtime>(mgr: &impl Manager<R>) -> Result<bool, sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    let count: i32 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM connection_configs WHERE secrets_encrypted AND (password != '' OR ssh_password != '' OR ssh_passphrase != '' OR sentinel_password != '' OR proxy_password != '')",
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(count > 0)
+}
 
+/// Inserts `config` under a new id, which is returned. Any id it carries is
+/// ignored.
 pub async fn create_connection_configuration<R: Runtime>(
     mgr: &impl Manager<R>,
     config: ConnectionConfig,
-) -> Result<(), sqlx::Error> {
+) -> Result<i64, sqlx::Error> {
     let pool = {
         let state = mgr.state::<Mutex<SqlitePool>>();
         let pool = state.lock().unwrap();
         pool.clone()
     };
 
-    sqlx::query("INSERT INTO connection_configs (name, host, port, username, password) VALUES (?, ?, ?, ?, ?)")
-        .bind(config.name)
-        .bind(config.host)
-        .bind(config.port)
-        .bind(config.username)
-        .bind(config.password)
-        .execute(&pool)
-        .await?;
+    // Held until the row is written, so the key cannot change in between.
+    let credentials = mgr.state::<AsyncMutex<Credentials>>();
+    let credentials = credentials.lock().await;
+    let config = encrypt_secrets(&credentials, config, None)?;
+    let sql = format!(
+        "INSERT INTO connection_configs (name, {}) VALUES (?{}) RETURNING id",
+        CONFIG_COLUMNS.join(", "),
+        ", ?".repeat(CONFIG_COLUMNS.len())
+    );
+    let query = sqlx::query(&sql).bind(&config.name);
+    let row = bind_config_columns(query, &config).fetch_one(&pool).await?;
 
-    Ok(())
+    Ok(row.get("id"))
 }
 
+/// Overwrites the connection with `config.id`, including its name. Fails
+/// with `RowNotFound` when there is no such connection.
 pub async fn update_connection_configuration<R: Runtime>(
     mgr: &impl Manager<R>,
     config: ConnectionConfig,
@@ -83,19 +219,33 @@ pub async fn update_connection_configuration<R: Runtime>(
         pool.clone()
     };
 
-    sqlx::query("UPDATE connection_configs SET name = ?, host = ?, port = ?, username = ?, password = ? WHERE name = ?")
-        .bind(&config.name)
-        .bind(config.host)
-        .bind(config.port)
-        .bind(config.username)
-        .bind(config.password)
-        .bind(&config.name)
+    // Held until the row is written, so the key cannot change in between.
+    let credentials = mgr.state::<AsyncMutex<Credentials>>();
+    let credentials = credentials.lock().await;
+    let id = config.id.ok_or(sqlx::Error::RowNotFound)?;
+    let stored = get_connection_configuration_by_id(mgr, id).await?;
+    let config = encrypt_secrets(&credentials, config, stored.as_ref())?;
+    let assignments: Vec<String> = CONFIG_COLUMNS
+        .iter()
+        .map(|column| format!("{} = ?", column))
+        .collect();
+    let sql = format!(
+        "UPDATE connection_configs SET name = ?, {}, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        assignments.join(", ")
+    );
+    let query = sqlx::query(&sql).bind(&config.name);
+    let result = bind_config_columns(query, &config)
+        .bind(config.id)
         .execute(&pool)
         .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
 
     Ok(())
 }
 
+/// Moves the connection to the trash, which frees its name.
 pub async fn delete_connection_configuration<R: Runtime>(
     mgr: &impl Manager<R>,
     name: String,
@@ -106,10 +256,315 @@ pub async fn delete_connection_configuration<R: Runtime>(
         pool.clone()
     };
 
-    sqlx::query("DELETE FROM connection_configs WHERE name = ?")
+    sqlx::query(
+        "UPDATE connection_configs SET deleted_at = CURRENT_TIMESTAMP WHERE name = ? AND deleted_at IS NULL",
+    )
+    .bind(name)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Takes the connection back out of the trash. Fails if another connection
+/// has taken its name since.
+pub async fn restore_connection_configuration<R: Runtime>(
+    mgr: &impl Manager<R>,
+    id: i64,
+) -> Result<(), sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    sqlx::query(
+        "UPDATE connection_configs SET deleted_at = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(id)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Permanently deletes a connection that is in the trash, or every one of
+/// them when `id` is `None`.
+pub async fn purge_connection_configurations<R: Runtime>(
+    mgr: &impl Manager<R>,
+    id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    sqlx::query(
+        "DELETE FROM connection_configs WHERE deleted_at IS NOT NULL AND (? IS NULL OR id = ?)",
+    )
+    .bind(id)
+    .bind(id)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records that the named connection was just opened.
+pub async fn touch_connection_last_connected<R: Runtime>(
+    mgr: &impl Manager<R>,
+    name: String,
+) -> Result<(), sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    sqlx::query(
+        "UPDATE connection_configs SET last_connected_at = CURRENT_TIMESTAMP WHERE name = ? AND deleted_at IS NULL",
+    )
+    .bind(name)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the position of each named connection in `names` as its sort order.
+pub async fn reorder_connection_configurations<R: Runtime>(
+    mgr: &impl Manager<R>,
+    names: &[String],
+) -> Result<(), sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    let mut tx = pool.begin().await?;
+    for (index, name) in names.iter().enumerate() {
+        sqlx::query(
+            "UPDATE connection_configs SET sort_order = ? WHERE name = ? AND deleted_at IS NULL",
+        )
+        .bind(index as i64)
         .bind(name)
-        .execute(&pool)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Rewrites only the secret columns of each config, in one transaction. The
+/// values are stored as given, so they must already be encrypted, and the
+/// caller must hold the credentials lock they were encrypted under.
+pub async fn update_connection_secrets<R: Runtime>(
+    mgr: &impl Manager<R>,
+    configs: &[ConnectionConfig],
+) -> Result<(), sqlx::Error> {
+    let pool = {
+        let state = mgr.state::<Mutex<SqlitePool>>();
+        let pool = state.lock().unwrap();
+        pool.clone()
+    };
+
+    let mut tx = pool.begin().await?;
+    for config in configs {
+        let ssh = config.ssh.as_ref();
+        sqlx::query(
+            "UPDATE connection_configs SET password = ?, ssh_password = ?, ssh_passphrase = ?, sentinel_password = ?, proxy_password = ?, secrets_encrypted = ? WHERE id = ?",
+        )
+        .bind(&config.password)
+        .bind(ssh.and_then(|ssh| ssh.password.as_ref()))
+        .bind(ssh.and_then(|ssh| ssh.passphrase.as_ref()))
+        .bind(config.sentinel.as_ref().and_then(|sentinel| sentinel.password.as_ref()))
+        .bind(config.proxy.as_ref().and_then(|proxy| proxy.password.as_ref()))
+        .bind(config.secrets_encrypted)
+        .bind(config.id)
+        .execute(&mut *tx)
         .await?;
+    }
+    tx.commit().await?;
 
     Ok(())
 }
+
+/// Encrypts secrets still stored as plain text by versions before encryption.
+pub async fn encrypt_stored_secrets<R: Runtime>(mgr: &impl Manager<R>) -> Result<(), sqlx::Error> {
+    let credentials = mgr.state::<AsyncMutex<Credentials>>();
+    let credentials = credentials.lock().await;
+    let configs = get_stored_connection_configurations(mgr)
+        .await?
+        .into_iter()
+        .filter(|config| !config.secrets_encrypted)
+        .map(|config| encrypt_secrets(&credentials, config, None))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    update_connection_secrets(mgr, &configs).await
+}
+
+/// Encrypts the secrets of `config`, which is saved over `stored`, if any.
+fn encrypt_secrets(
+    credentials: &Credentials,
+    mut config: ConnectionConfig,
+    stored: Option<&ConnectionConfig>,
+) -> Result<ConnectionConfig, sqlx::Error> {
+    credentials
+        .decrypt_submitted(&mut config, stored)
+        .and_then(|_| credentials.encrypt_config(&mut config))
+        .map_err(|e| sqlx::Error::Encode(e.into()))?;
+
+    Ok(config)
+}
+
+fn select_sql() -> String {
+    format!(
+        "SELECT id, name, {}, {} FROM connection_configs",
+        CONFIG_COLUMNS.join(", "),
+        METADATA_COLUMNS.join(", ")
+    )
+}
+
+fn bind_config_columns<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    config: &'q ConnectionConfig,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    let ssh = config.ssh.as_ref();
+    let sentinel = config.sentinel.as_ref();
+    let proxy = config.proxy.as_ref();
+
+    query
+        .bind(&config.host)
+        .bind(config.port)
+        .bind(&config.username)
+        .bind(&config.password)
+        .bind(config.database)
+        .bind(config.tls_enabled)
+        .bind(&config.tls_ca_cert_path)
+        .bind(&config.tls_client_cert_path)
+        .bind(&config.tls_client_key_path)
+        .bind(config.tls_skip_verify)
+        .bind(&config.tls_server_name)
+        .bind(ssh.map(|ssh| &ssh.host))
+        .bind(ssh.map(|ssh| ssh.port))
+        .bind(ssh.map(|ssh| &ssh.username))
+        .bind(ssh.and_then(|ssh| ssh.password.as_ref()))
+        .bind(ssh.and_then(|ssh| ssh.private_key_path.as_ref()))
+        .bind(ssh.and_then(|ssh| ssh.passphrase.as_ref()))
+        .bind(ssh.and_then(|ssh| ssh.host_key_fingerprint.as_ref()))
+        .bind(config.mode.as_str())
+        .bind(sentinel.map(|sentinel| &sentinel.master_name))
+        .bind(sentinel.map(|sentinel| serde_json::to_string(&sentinel.nodes).unwrap_or_default()))
+        .bind(sentinel.and_then(|sentinel| sentinel.username.as_ref()))
+        .bind(sentinel.and_then(|sentinel| sentinel.password.as_ref()))
+        .bind(sentinel.is_some_and(|sentinel| sentinel.read_from_replica))
+        .bind(&config.socket_path)
+        .bind(config.resp3)
+        .bind(config.connect_timeout_secs)
+        .bind(config.response_timeout_secs)
+        .bind(config.keepalive_secs)
+        .bind(&config.client_name)
+        .bind(&config.group)
+        .bind(serde_json::to_string(&config.tags).unwrap_or_default())
+        .bind(&config.color)
+        .bind(config.sort_order)
+        .bind(config.read_only)
+        .bind(config.environment.as_str())
+        .bind(serde_json::to_string(&config.key_delimiters).unwrap_or_default())
+        .bind(config.flat_key_list)
+        .bind(proxy.map(|proxy| proxy.kind.as_str()))
+        .bind(proxy.map(|proxy| &proxy.host))
+        .bind(proxy.map(|proxy| proxy.port))
+        .bind(proxy.and_then(|proxy| proxy.username.as_ref()))
+        .bind(proxy.and_then(|proxy| proxy.password.as_ref()))
+        .bind(config.secrets_encrypted)
+}
+
+fn connection_config_from_row(row: &SqliteRow) -> ConnectionConfig {
+    let ssh = row
+        .get::<Option<String>, _>("ssh_host")
+        .map(|host| SshConfig {
+            host,
+            port: row.get::<Option<u16>, _>("ssh_port").unwrap_or(22),
+            username: row.get::<Option<String>, _>("ssh_username").unwrap_or_default(),
+            password: row.get("ssh_password"),
+            private_key_path: row.get("ssh_private_key_path"),
+            passphrase: row.get("ssh_passphrase"),
+            host_key_fingerprint: row.get("ssh_host_key_fingerprint"),
+        });
+
+    let sentinel = row
+        .get::<Option<String>, _>("sentinel_master_name")
+        .map(|master_name| SentinelConfig {
+            master_name,
+            nodes: row
+                .get::<Option<String>, _>("sentinel_nodes")
+                .and_then(|nodes| serde_json::from_str(&nodes).ok())
+                .unwrap_or_default(),
+            username: row.get("sentinel_username"),
+            password: row.get("sentinel_password"),
+            read_from_replica: row.get("sentinel_read_from_replica"),
+        });
+
+    let proxy = row
+        .get::<Option<String>, _>("proxy_host")
+        .map(|host| ProxyConfig {
+            kind: row
+                .get::<Option<String>, _>("proxy_kind")
+                .unwrap_or_default()
+                .as_str()
+                .into(),
+            host,
+            port: row.get::<Option<u16>, _>("proxy_port").unwrap_or(1080),
+            username: row.get("proxy_username"),
+            password: row.get("proxy_password"),
+        });
+
+    ConnectionConfig {
+        id: row.get("id"),
+        name: row.get("name"),
+        host: row.get("host"),
+        port: row.get("port"),
+        username: row.get("username"),
+        password: row.get("password"),
+        database: row.get("database"),
+        tls_enabled: row.get("tls_enabled"),
+        tls_ca_cert_path: row.get("tls_ca_cert_path"),
+        tls_client_cert_path: row.get("tls_client_cert_path"),
+        tls_client_key_path: row.get("tls_client_key_path"),
+        tls_skip_verify: row.get("tls_skip_verify"),
+        tls_server_name: row.get("tls_server_name"),
+        ssh,
+        proxy,
+        mode: row.get::<String, _>("mode").as_str().into(),
+        sentinel,
+        socket_path: row.get("socket_path"),
+        resp3: row.get("resp3"),
+        connect_timeout_secs: row.get("connect_timeout_secs"),
+        response_timeout_secs: row.get("response_timeout_secs"),
+        keepalive_secs: row.get("keepalive_secs"),
+        client_name: row.get("client_name"),
+        group: row.get("group_name"),
+        tags: row
+            .get::<Option<String>, _>("tags")
+            .and_then(|tags| serde_json::from_str(&tags).ok())
+            .unwrap_or_default(),
+        color: row.get("color"),
+        sort_order: row.get("sort_order"),
+        read_only: row.get("read_only"),
+        environment: row.get::<String, _>("environment").as_str().into(),
+        key_delimiters: row
+            .get::<Option<String>, _>("key_delimiters")
+            .and_then(|delimiters| serde_json::from_str(&delimiters).ok())
+            .unwrap_or_default(),
+        flat_key_list: row.get("flat_key_list"),
+        secrets_encrypted: row.get("secrets_encrypted"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        deleted_at: row.get("deleted_at"),
+        last_connected_at: row.get("last_connected_at"),
+    }
+}
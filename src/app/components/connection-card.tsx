@@ -1,22 +1,55 @@
-import { Cog, Trash2, Unplug } from "lucide-react";
+import { Cog, Lock, Trash2, Unplug } from "lucide-react";
+import { ConnectionMode, Environment } from "@/models/connection";
 
 interface ConnectionCardProps {
     name: string;
     address: string;
+    mode?: ConnectionMode;
+    tags?: string[];
+    color?: string | null;
+    environment?: Environment;
+    readOnly?: boolean;
     status: string;
     onPlay?: () => void;
     onSettings?: () => void;
     onDelete?: () => void;
 }
 
-export default function ConnectionCard({ name, address, status, onPlay, onSettings, onDelete }: ConnectionCardProps) {
+export default function ConnectionCard({ name, address, mode, tags, color, environment, readOnly, status, onPlay, onSettings, onDelete }: ConnectionCardProps) {
     return (
-        <div className="bg-gradient-to-br from-gray-50 to-gray-100 dark:from-gray-800 dark:to-gray-900 rounded-lg p-4 w-[220px] h-[200px] flex flex-col shadow-lg shadow-black/10 backdrop-blur-sm">
+        <div
+            className="bg-gradient-to-br from-gray-50 to-gray-100 dark:from-gray-800 dark:to-gray-900 rounded-lg p-4 w-[220px] h-[200px] flex flex-col shadow-lg shadow-black/10 backdrop-blur-sm border-t-4 border-transparent"
+            style={color ? { borderTopColor: color } : undefined}
+        >
             {/* Card Header */}
-            <h3 className="text-gray-900 dark:text-white text-xl font-medium mb-2">{name}</h3>
+            <div className="flex items-center gap-2 mb-2">
+                <h3 className="text-gray-900 dark:text-white text-xl font-medium truncate">{name}</h3>
+                {readOnly && <Lock className="w-4 h-4 shrink-0 text-gray-500 dark:text-gray-400" />}
+                {environment && environment !== 'dev' && (
+                    <span className={`text-xs px-1.5 rounded uppercase shrink-0 ${environment === 'prod' ? 'bg-red-600 text-white' : 'bg-amber-500 text-white'}`}>
+                        {environment}
+                    </span>
+                )}
+            </div>
 
             {/* Connection Info */}
-            <p className="text-gray-600 dark:text-gray-400 text-sm mb-2">{address}</p>
+            <div className="flex items-center gap-2 mb-2">
+                <p className="text-gray-600 dark:text-gray-400 text-sm truncate" title={address}>{address}</p>
+                {mode && mode !== 'standalone' && (
+                    <span className="text-xs px-1.5 rounded uppercase shrink-0 bg-blue-600 text-white">
+                        {mode}
+                    </span>
+                )}
+            </div>
+            {tags && tags.length > 0 && (
+                <div className="flex gap-1 mb-2 overflow-hidden">
+                    {tags.map((tag) => (
+                        <span key={tag} className="text-xs px-1.5 rounded bg-gray-200 dark:bg-gray-700 text-gray-600 dark:text-gray-300 whitespace-nowrap">
+                            {tag}
+                        </span>
+                    ))}
+                </div>
+            )}
             <p className="text-gray-600 dark:text-gray-400 mb-auto">{status}</p>
 
             {/* Action Buttons */}
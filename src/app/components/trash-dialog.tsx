@@ -0,0 +1,93 @@
+import { Button } from "@/components/ui/button";
+import { CustomDialog } from "@/components/ui/custom-dialog";
+import { ConnectionConfig } from "@/models/connection";
+import { connectionCommands } from "@/services/connection-commands";
+import { useEffect, useState } from "react";
+import { toast } from "sonner";
+
+interface TrashDialogProps {
+    isOpen: boolean;
+    onClose: () => void;
+    onRestored: () => void;
+}
+
+// SQLite timestamps are UTC without a zone, e.g. "2026-10-18 09:30:00".
+const formatTimestamp = (timestamp?: string | null) =>
+    timestamp ? new Date(`${timestamp.replace(' ', 'T')}Z`).toLocaleString() : 'never';
+
+export default function TrashDialog({ isOpen, onClose, onRestored }: TrashDialogProps) {
+    const [configs, setConfigs] = useState<ConnectionConfig[]>([]);
+
+    const loadTrash = () => {
+        connectionCommands.loadDeletedConfigs()
+            .then(setConfigs)
+            .catch((error) => toast.error(`Failed to load the trash: ${error}`));
+    };
+
+    useEffect(() => {
+        if (isOpen) {
+            loadTrash();
+        }
+    }, [isOpen]);
+
+    const handleRestore = (id: number) => {
+        connectionCommands.restoreConfig(id)
+            .then(() => {
+                loadTrash();
+                onRestored();
+            })
+            .catch((error) => toast.error(`Failed to restore connection: ${error}`));
+    };
+
+    const handlePurge = (id?: number) => {
+        connectionCommands.purgeConfigs(id)
+            .then(loadTrash)
+            .catch((error) => toast.error(`Failed to delete connection: ${error}`));
+    };
+
+    return (
+        <CustomDialog isOpen={isOpen} onClose={onClose} title="Trash">
+            <div className="space-y-2 max-h-80 overflow-y-auto custom-scrollbar">
+                {configs.length === 0 && (
+                    <p className="text-gray-500 dark:text-gray-400">The trash is empty.</p>
+                )}
+                {configs.map((config) => (
+                    <div key={config.id} className="flex items-center gap-2">
+                        <div className="flex-1 min-w-0">
+                            <p className="truncate">{config.name}</p>
+                            <p className="text-xs text-gray-500 dark:text-gray-400">
+                                Deleted {formatTimestamp(config.deletedAt)}, last connected {formatTimestamp(config.lastConnectedAt)}
+                            </p>
+                        </div>
+                        <Button
+                            type="button"
+                            variant="secondary"
+                            onClick={() => config.id != null && handleRestore(config.id)}
+                            className="bg-gray-200 hover:bg-gray-300 dark:bg-[#4c4c4c] dark:hover:bg-[#5c5c5c] text-gray-900 dark:text-white px-3"
+                        >
+                            Restore
+                        </Button>
+                        <Button
+                            type="button"
+                            variant="secondary"
+                            onClick={() => config.id != null && handlePurge(config.id)}
+                            className="bg-gray-100 hover:bg-gray-200 dark:bg-[#2c2c2c] dark:hover:bg-[#3c3c3c] text-red-600 px-3"
+                        >
+                            Delete
+                        </Button>
+                    </div>
+                ))}
+            </div>
+            <div className="flex justify-end pt-4">
+                <Button
+                    type="button"
+                    onClick={() => handlePurge()}
+                    disabled={configs.length === 0}
+                    className="bg-red-600 hover:bg-red-700 text-white px-4"
+                >
+                    Empty Trash
+                </Button>
+            </div>
+        </CustomDialog>
+    );
+}
@@ -1,6 +1,8 @@
 import React, { useState } from 'react';
 import { ChevronRight, ChevronDown } from 'lucide-react';
 import { RedisTreeItem } from '@/models/redisTreeItem';
+import { ScanCursor, ScanPage } from '@/models/keyScan';
+import { mergeTrees } from '@/lib/key-tree';
 import RedisItem from './redisitem';
 
 interface TreeViewProps {
@@ -8,13 +10,35 @@ interface TreeViewProps {
   onDelete: (name: string) => void;
   selectedItemName: string;
   onItemSelect: (item: RedisTreeItem) => void;
+  // Lists a page of the children of folders that only carry a key count,
+  // resuming from `cursor`.
+  onLoadChildren?: (item: RedisTreeItem, cursor: ScanCursor | null) => Promise<ScanPage>;
 }
 
-const TreeView: React.FC<TreeViewProps> = ({ item, onDelete, selectedItemName, onItemSelect }) => {
+const TreeView: React.FC<TreeViewProps> = ({ item, onDelete, selectedItemName, onItemSelect, onLoadChildren }) => {
   const [isExpanded, setIsExpanded] = useState(false);
-  const hasChildren = item.children && item.children.length > 0;
+  const [loadedChildren, setLoadedChildren] = useState<RedisTreeItem[]>([]);
+  // Where to resume listing the children, null once all were listed.
+  const [childrenCursor, setChildrenCursor] = useState<ScanCursor | null>(null);
+  const isLazyFolder = item.kind === 'folder' && item.children === undefined;
+  const children = isLazyFolder ? loadedChildren : item.children ?? [];
+  const isFolder = item.kind === 'folder';
 
-  if (!hasChildren && item.item_type) {
+  const loadChildren = (cursor: ScanCursor | null) => {
+    onLoadChildren?.(item, cursor).then((page) => {
+      setLoadedChildren(prev => cursor ? mergeTrees(prev, page.items) : page.items);
+      setChildrenCursor(page.cursor);
+    });
+  };
+
+  const handleToggle = () => {
+    if (!isExpanded && isLazyFolder) {
+      loadChildren(null);
+    }
+    setIsExpanded(!isExpanded);
+  };
+
+  if (item.kind === 'key') {
     return (
       <RedisItem
         item={item}
@@ -29,9 +53,9 @@ const TreeView: React.FC<TreeViewProps> = ({ item, onDelete, selectedItemName, o
     <div>
       <div
         className="flex items-center gap-1 hover:bg-gray-100 dark:hover:bg-gray-800 rounded-md p-1 cursor-pointer"
-        onClick={() => setIsExpanded(!isExpanded)}
+        onClick={handleToggle}
       >
-        {hasChildren && (
+        {isFolder && (
           <span className="text-gray-800 dark:text-gray-400">
             {isExpanded ? (
               <ChevronDown className="w-4 h-4" />
@@ -41,19 +65,31 @@ const TreeView: React.FC<TreeViewProps> = ({ item, onDelete, selectedItemName, o
           </span>
         )}
         <span className="text-gray-800 dark:text-gray-200 text-sm">{item.label}</span>
+        {item.key_count !== undefined && (
+          <span className="text-gray-500 text-xs">({item.key_count})</span>
+        )}
       </div>
 
-      {isExpanded && hasChildren && (
+      {isExpanded && isFolder && (
         <div className="ml-4 flex flex-col gap-1 mt-1">
-          {item.children!.map((child) => (
+          {children.map((child) => (
             <TreeView
-              key={child.key}
+              key={`${child.kind}:${child.key}`}
               item={child}
               onDelete={onDelete}
               selectedItemName={selectedItemName}
               onItemSelect={onItemSelect}
+              onLoadChildren={onLoadChildren}
             />
           ))}
+          {isLazyFolder && childrenCursor && (
+            <button
+              className="text-left text-xs text-gray-500 hover:text-gray-700 dark:hover:text-gray-300 p-1"
+              onClick={() => loadChildren(childrenCursor)}
+            >
+              Load more
+            </button>
+          )}
         </div>
       )}
     </div>
@@ -1,15 +1,19 @@
 'use client';
-import { PlusIcon, Server, Tag } from "lucide-react";
+import { PlusIcon, Server, Tag, Trash2 } from "lucide-react";
 import ConnectionCard from "../components/connection-card";
 import { useEffect, useState } from "react";
 import { CustomDialog } from "@/components/ui/custom-dialog";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
+import { Switch } from "@/components/ui/switch";
 import { UserIcon, KeyIcon } from "lucide-react";
 import { connectionCommands } from "@/services/connection-commands";
 import CustomAlertDialog from "../components/custom-alert-dialog";
+import TrashDialog from "../components/trash-dialog";
 import { LoadingDialog } from "@/components/ui/loading-dialog";
 import { toast } from "sonner";
+import { ConnectionConfig, ConnectionMode, Environment, ProxyKind } from "@/models/connection";
 
 interface Connection {
     id: string;
@@ -18,37 +22,216 @@ interface Connection {
     status: string;
     username: string;
     password: string;
+    config: ConnectionConfig;
 }
 
+const formatAddress = (config: ConnectionConfig) =>
+    config.mode === 'unix' ? config.socketPath || '' : `${config.host}:${config.port}`;
+
+const parseTags = (tags: string) =>
+    tags.split(',').map(tag => tag.trim()).filter(tag => tag.length > 0);
+
+// Delimiters are entered separated by spaces, as commas may be delimiters.
+const parseDelimiters = (delimiters: string) =>
+    delimiters.split(/\s+/).filter(delimiter => delimiter.length > 0);
+
+// Sentinel addresses are entered as `host:port`, separated by commas or spaces.
+const parseNodes = (nodes: string) =>
+    nodes.split(/[\s,]+/).filter(node => node.length > 0);
+
+const optionalText = (value: string) => value.trim() || null;
+
+// Empty leaves the backend default in place.
+const optionalSeconds = (value: string) => value.trim() ? Number(value) : null;
+
+const inputClassName = "bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600";
+
+const EMPTY_FORM = {
+    name: '',
+    host: '127.0.0.1',
+    port: '6379',
+    username: '',
+    password: '',
+    group: '',
+    tags: '',
+    color: '',
+    environment: 'dev' as Environment,
+    readOnly: false,
+    keyDelimiters: '',
+    flatKeyList: false,
+    mode: 'standalone' as ConnectionMode,
+    socketPath: '',
+    sentinelMasterName: '',
+    sentinelNodes: '',
+    sentinelUsername: '',
+    sentinelPassword: '',
+    sentinelReadFromReplica: false,
+    tlsEnabled: false,
+    tlsCaCertPath: '',
+    tlsClientCertPath: '',
+    tlsClientKeyPath: '',
+    tlsServerName: '',
+    tlsSkipVerify: false,
+    sshEnabled: false,
+    sshHost: '',
+    sshPort: '22',
+    sshUsername: '',
+    sshPassword: '',
+    sshPrivateKeyPath: '',
+    sshPassphrase: '',
+    sshHostKeyFingerprint: '',
+    proxyKind: 'none' as ProxyKind | 'none',
+    proxyHost: '',
+    proxyPort: '1080',
+    proxyUsername: '',
+    proxyPassword: '',
+    connectTimeoutSecs: '',
+    responseTimeoutSecs: '',
+    keepaliveSecs: ''
+};
+
+type ConnectionForm = typeof EMPTY_FORM;
+
+const formFromConfig = (config: ConnectionConfig): ConnectionForm => ({
+    name: config.name,
+    host: config.host,
+    port: String(config.port),
+    username: config.username || '',
+    password: config.password || '',
+    group: config.group || '',
+    tags: (config.tags || []).join(', '),
+    color: config.color || '',
+    environment: config.environment || 'dev',
+    readOnly: config.readOnly || false,
+    keyDelimiters: (config.keyDelimiters || []).join(' '),
+    flatKeyList: config.flatKeyList || false,
+    mode: config.mode || 'standalone',
+    socketPath: config.socketPath || '',
+    sentinelMasterName: config.sentinel?.masterName || '',
+    sentinelNodes: (config.sentinel?.nodes || []).join(', '),
+    sentinelUsername: config.sentinel?.username || '',
+    sentinelPassword: config.sentinel?.password || '',
+    sentinelReadFromReplica: config.sentinel?.readFromReplica || false,
+    tlsEnabled: config.tlsEnabled || false,
+    tlsCaCertPath: config.tlsCaCertPath || '',
+    tlsClientCertPath: config.tlsClientCertPath || '',
+    tlsClientKeyPath: config.tlsClientKeyPath || '',
+    tlsServerName: config.tlsServerName || '',
+    tlsSkipVerify: config.tlsSkipVerify || false,
+    sshEnabled: !!config.ssh,
+    sshHost: config.ssh?.host || '',
+    sshPort: String(config.ssh?.port ?? 22),
+    sshUsername: config.ssh?.username || '',
+    sshPassword: config.ssh?.password || '',
+    sshPrivateKeyPath: config.ssh?.privateKeyPath || '',
+    sshPassphrase: config.ssh?.passphrase || '',
+    sshHostKeyFingerprint: config.ssh?.hostKeyFingerprint || '',
+    proxyKind: config.proxy?.kind || 'none',
+    proxyHost: config.proxy?.host || '',
+    proxyPort: String(config.proxy?.port ?? 1080),
+    proxyUsername: config.proxy?.username || '',
+    proxyPassword: config.proxy?.password || '',
+    connectTimeoutSecs: config.connectTimeoutSecs ? String(config.connectTimeoutSecs) : '',
+    responseTimeoutSecs: config.responseTimeoutSecs ? String(config.responseTimeoutSecs) : '',
+    keepaliveSecs: config.keepaliveSecs ? String(config.keepaliveSecs) : ''
+});
+
+// Settings the form does not edit, such as timestamps, are kept from `base`.
+const configFromForm = (form: ConnectionForm, base?: Partial<ConnectionConfig>): ConnectionConfig => ({
+    ...base,
+    name: form.name,
+    host: form.host,
+    port: Number(form.port),
+    username: form.username,
+    password: form.password,
+    group: optionalText(form.group),
+    tags: parseTags(form.tags),
+    color: optionalText(form.color),
+    environment: form.environment,
+    readOnly: form.readOnly,
+    keyDelimiters: parseDelimiters(form.keyDelimiters),
+    flatKeyList: form.flatKeyList,
+    mode: form.mode,
+    socketPath: form.mode === 'unix' ? optionalText(form.socketPath) : null,
+    sentinel: form.mode === 'sentinel'
+        ? {
+            masterName: form.sentinelMasterName.trim(),
+            nodes: parseNodes(form.sentinelNodes),
+            username: optionalText(form.sentinelUsername),
+            password: form.sentinelPassword || null,
+            readFromReplica: form.sentinelReadFromReplica
+        }
+        : null,
+    tlsEnabled: form.mode !== 'unix' && form.tlsEnabled,
+    tlsCaCertPath: optionalText(form.tlsCaCertPath),
+    tlsClientCertPath: optionalText(form.tlsClientCertPath),
+    tlsClientKeyPath: optionalText(form.tlsClientKeyPath),
+    tlsServerName: optionalText(form.tlsServerName),
+    tlsSkipVerify: form.tlsSkipVerify,
+    ssh: form.mode === 'standalone' && form.sshEnabled
+        ? {
+            host: form.sshHost.trim(),
+            port: Number(form.sshPort) || 22,
+            username: form.sshUsername,
+            password: form.sshPassword || null,
+            privateKeyPath: optionalText(form.sshPrivateKeyPath),
+            passphrase: form.sshPassphrase || null,
+            hostKeyFingerprint: optionalText(form.sshHostKeyFingerprint)
+        }
+        : null,
+    proxy: form.mode === 'standalone' && form.proxyKind !== 'none'
+        ? {
+            kind: form.proxyKind,
+            host: form.proxyHost.trim(),
+            port: Number(form.proxyPort) || 1080,
+            username: optionalText(form.proxyUsername),
+            password: form.proxyPassword || null
+        }
+        : null,
+    connectTimeoutSecs: optionalSeconds(form.connectTimeoutSecs),
+    responseTimeoutSecs: optionalSeconds(form.responseTimeoutSecs),
+    keepaliveSecs: optionalSeconds(form.keepaliveSecs)
+});
+
+// Grouped connections by folder name, each in the order the backend returned them.
+const groupConnections = (connections: Connection[]) => {
+    const groups = new Map<string, Connection[]>();
+    for (const connection of connections) {
+        const group = connection.config.group;
+        if (group) {
+            groups.set(group, [...(groups.get(group) || []), connection]);
+        }
+    }
+    return Array.from(groups.entries()).sort(([a], [b]) => a.localeCompare(b));
+};
+
 export default function Connection() {
     const [isDialogOpen, setIsDialogOpen] = useState(false);
     const [error, setError] = useState<string | null>(null);
     const [successMessage, setSuccessMessage] = useState<string | null>(null);
     const [connections, setConnections] = useState<Connection[]>([]);
-    const [formData, setFormData] = useState({
-        name: '',
-        host: '127.0.0.1',
-        port: '6379',
-        username: '',
-        password: ''
-    });
+    const [formData, setFormData] = useState(EMPTY_FORM);
     const [deleteConfirmation, setDeleteConfirmation] = useState<{ open: boolean, connectionName: string }>({
         open: false,
         connectionName: ''
     });
     const [isEditMode, setIsEditMode] = useState(false);
+    // The name of the connection being edited, which the form may change.
+    const [editingName, setEditingName] = useState('');
     const [isTestingConnection, setIsTestingConnection] = useState(false);
+    const [isTrashOpen, setIsTrashOpen] = useState(false);
 
-    useEffect(() => {
+    const loadConnections = () => {
         connectionCommands.loadConfig()
             .then((configs) => {
-                const loadedConnections = configs.map((config: any) => ({
-                    id: config.name,
+                const loadedConnections = configs.map((config: ConnectionConfig) => ({
+                    id: String(config.id ?? config.name),
                     name: config.name,
-                    address: `${config.host}:${config.port}`,
+                    address: formatAddress(config),
                     status: 'Disconnected',
                     username: config.username || '',
-                    password: config.password || ''
+                    password: config.password || '',
+                    config
                 }));
                 setConnections(loadedConnections);
                 console.log('Config loaded successfully:', configs);
@@ -56,7 +239,12 @@ export default function Connection() {
             .catch((error) => {
                 console.error('Error loading config:', error);
             });
-    }, []);
+    };
+
+    useEffect(loadConnections, []);
+
+    const setField = <K extends keyof ConnectionForm>(name: K, value: ConnectionForm[K]) =>
+        setFormData(prev => ({ ...prev, [name]: value }));
 
     const handleInputChange = (e: React.ChangeEvent<HTMLInputElement>) => {
         const { name, value } = e.target;
@@ -68,46 +256,41 @@ export default function Connection() {
     };
 
     const handleSettings = (connection: Connection) => {
-        setFormData({
-            name: connection.name,
-            host: connection.address.split(':')[0],
-            port: connection.address.split(':')[1],
-            username: connection.username,
-            password: connection.password,
-        });
+        setFormData(formFromConfig(connection.config));
+        setEditingName(connection.name);
         setIsEditMode(true);
         setIsDialogOpen(true);
     };
 
     const handleSave = async () => {
         try {
-            await connectionCommands.saveConfig({
-                name: formData.name,
-                host: formData.host,
-                port: Number(formData.port),
-                username: formData.username,
-                password: formData.password
-            }, !isEditMode);
+            const existing = connections.find(conn => conn.name === editingName)?.config;
+            const config = configFromForm(formData, isEditMode ? existing : { sortOrder: connections.length });
+            const id = await connectionCommands.saveConfig(config, !isEditMode);
+            config.id = id;
 
             if (isEditMode) {
                 setConnections(prev => prev.map(conn =>
-                    conn.name === formData.name
+                    conn.name === editingName
                         ? {
                             ...conn,
-                            address: `${formData.host}:${formData.port}`,
+                            name: formData.name,
+                            address: formatAddress(config),
                             username: formData.username,
-                            password: formData.password
+                            password: formData.password,
+                            config
                         }
                         : conn
                 ));
             } else {
                 setConnections(prev => [...prev, {
-                    id: formData.name,
+                    id: String(id),
                     name: formData.name,
-                    address: `${formData.host}:${formData.port}`,
+                    address: formatAddress(config),
                     status: 'Disconnected',
                     username: formData.username,
-                    password: formData.password
+                    password: formData.password,
+                    config
                 }]);
             }
 
@@ -119,13 +302,8 @@ export default function Connection() {
     };
 
     const handleClose = () => {
-        setFormData({
-            name: '',
-            host: '127.0.0.1',
-            port: '6379',
-            username: '',
-            password: ''
-        });
+        setFormData(EMPTY_FORM);
+        setEditingName('');
         setIsEditMode(false);
         setIsDialogOpen(false);
     };
@@ -150,13 +328,8 @@ export default function Connection() {
 
     const handleTestConnection = () => {
         setIsTestingConnection(true);
-        connectionCommands.testConnection({
-            name: formData.name,
-            host: formData.host,
-            port: Number(formData.port),
-            username: formData.username,
-            password: formData.password
-        })
+        const existing = isEditMode ? connections.find(conn => conn.name === editingName)?.config : undefined;
+        connectionCommands.testConnection(configFromForm(formData, existing))
             .then(() => {
                 setSuccessMessage('Connection successful!');
             })
@@ -169,13 +342,7 @@ export default function Connection() {
     };
 
     const handleConnectToRedis = (connection: Connection) => {
-        connectionCommands.connectToRedis({
-            name: connection.name,
-            host: connection.address.split(':')[0],
-            port: Number(connection.address.split(':')[1]),
-            username: connection.username,
-            password: connection.password
-        }).then(() => {
+        connectionCommands.connectToRedis(connection.config).then(() => {
             toast.success('Connected to Redis!');
         }).catch((error) => {
             toast.error(`Failed to connect to Redis: ${error}`);
@@ -184,12 +351,51 @@ export default function Connection() {
 
     return (
         <div className="h-[calc(100vh-2rem)] overflow-y-auto custom-scrollbar bg-white/30 dark:bg-gray-800/50">
+            <div className="flex justify-end px-4 pt-4">
+                <button
+                    onClick={() => setIsTrashOpen(true)}
+                    className="flex items-center gap-1 p-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 text-gray-500 dark:text-gray-400 hover:text-gray-900 dark:hover:text-white transition-colors"
+                >
+                    <Trash2 className="w-4 h-4" />
+                    Trash
+                </button>
+            </div>
+
+            {groupConnections(connections).map(([group, members]) => (
+                <div key={group} className="px-4 pt-4">
+                    <h2 className="text-gray-700 dark:text-gray-300 text-sm font-medium uppercase">{group}</h2>
+                    <div className="grid auto-rows-[200px] grid-cols-[repeat(auto-fill,minmax(220px,1fr))] gap-4 pt-2">
+                        {members.map((connection) => (
+                            <ConnectionCard
+                                key={connection.id}
+                                name={connection.name}
+                                address={connection.address}
+                                mode={connection.config.mode}
+                                tags={connection.config.tags}
+                                color={connection.config.color}
+                                environment={connection.config.environment}
+                                readOnly={connection.config.readOnly}
+                                status={connection.status}
+                                onPlay={() => handleConnectToRedis(connection)}
+                                onSettings={() => handleSettings(connection)}
+                                onDelete={() => handleDelete(connection.name)}
+                            />
+                        ))}
+                    </div>
+                </div>
+            ))}
+
             <div className="grid auto-rows-[200px] grid-cols-[repeat(auto-fill,minmax(220px,1fr))] gap-4 p-4">
-                {connections.map((connection) => (
+                {connections.filter((connection) => !connection.config.group).map((connection) => (
                     <ConnectionCard
                         key={connection.id}
                         name={connection.name}
                         address={connection.address}
+                        mode={connection.config.mode}
+                        tags={connection.config.tags}
+                        color={connection.config.color}
+                        environment={connection.config.environment}
+                        readOnly={connection.config.readOnly}
                         status={connection.status}
                         onPlay={() => handleConnectToRedis(connection)}
                         onSettings={() => handleSettings(connection)}
@@ -205,7 +411,7 @@ export default function Connection() {
                 </button>
             </div>
 
-            <CustomDialog isOpen={isDialogOpen} onClose={handleClose} title={isEditMode ? 'Update Connection' : 'New Connection'}>
+            <CustomDialog isOpen={isDialogOpen} onClose={handleClose} title={isEditMode ? 'Update Connection' : 'New Connection'} className="max-h-[90vh] overflow-y-auto custom-scrollbar">
                 <form className="space-y-5">
                     <div className="space-y-2">
                         <label className="text-base">Connection Name</label>
@@ -217,37 +423,127 @@ export default function Connection() {
                                 onChange={handleInputChange}
                                 placeholder="connection name"
                                 className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none pl-10 h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
-                                disabled={isEditMode}
                             />
                         </div>
                     </div>
 
-                    <div className="flex gap-4">
-                        <div className="flex-1 space-y-2">
-                            <label className="text-base">Host</label>
-                            <div className="relative">
-                                <Server className="absolute left-3 top-1/2 transform -translate-y-1/2 w-5 h-5 text-gray-500 dark:text-gray-400" />
-                                <Input
-                                    name="host"
-                                    value={formData.host}
-                                    onChange={handleInputChange}
-                                    placeholder="127.0.0.1"
-                                    className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none pl-10 h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
-                                />
-                            </div>
-                        </div>
+                    <div className="space-y-2">
+                        <label className="text-base">Mode</label>
+                        <Select value={formData.mode} onValueChange={(value) => setField('mode', value as ConnectionMode)}>
+                            <SelectTrigger className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10">
+                                <SelectValue />
+                            </SelectTrigger>
+                            <SelectContent>
+                                <SelectItem value="standalone">Standalone</SelectItem>
+                                <SelectItem value="sentinel">Sentinel</SelectItem>
+                                <SelectItem value="cluster">Cluster</SelectItem>
+                                <SelectItem value="unix">Unix socket</SelectItem>
+                            </SelectContent>
+                        </Select>
+                    </div>
 
-                        <div className="w-32 space-y-2">
-                            <label className="text-base">Port</label>
+                    {formData.mode === 'unix' && (
+                        <div className="space-y-2">
+                            <label className="text-base">Socket Path</label>
                             <Input
-                                name="port"
-                                value={formData.port}
+                                name="socketPath"
+                                value={formData.socketPath}
                                 onChange={handleInputChange}
-                                placeholder="6379"
-                                className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
+                                placeholder="/var/run/redis/redis.sock"
+                                className={inputClassName}
                             />
                         </div>
-                    </div>
+                    )}
+
+                    {formData.mode === 'sentinel' && (
+                        <>
+                            <div className="flex gap-4">
+                                <div className="flex-1 space-y-2">
+                                    <label className="text-base">Master Name</label>
+                                    <Input
+                                        name="sentinelMasterName"
+                                        value={formData.sentinelMasterName}
+                                        onChange={handleInputChange}
+                                        placeholder="mymaster"
+                                        className={inputClassName}
+                                    />
+                                </div>
+
+                                <div className="flex-1 space-y-2">
+                                    <label className="text-base">Sentinel Nodes</label>
+                                    <Input
+                                        name="sentinelNodes"
+                                        value={formData.sentinelNodes}
+                                        onChange={handleInputChange}
+                                        placeholder="10.0.0.1:26379, 10.0.0.2:26379"
+                                        className={inputClassName}
+                                    />
+                                </div>
+                            </div>
+
+                            <div className="flex gap-4 items-end">
+                                <div className="flex-1 space-y-2">
+                                    <label className="text-base">Sentinel Username</label>
+                                    <Input
+                                        name="sentinelUsername"
+                                        value={formData.sentinelUsername}
+                                        onChange={handleInputChange}
+                                        placeholder="username"
+                                        className={inputClassName}
+                                    />
+                                </div>
+
+                                <div className="flex-1 space-y-2">
+                                    <label className="text-base">Sentinel Password</label>
+                                    <Input
+                                        name="sentinelPassword"
+                                        value={formData.sentinelPassword}
+                                        onChange={handleInputChange}
+                                        type="password"
+                                        placeholder="password"
+                                        className={inputClassName}
+                                    />
+                                </div>
+                            </div>
+
+                            <label className="flex items-center gap-2 h-10 text-base">
+                                <Switch
+                                    checked={formData.sentinelReadFromReplica}
+                                    onCheckedChange={(checked) => setField('sentinelReadFromReplica', checked)}
+                                />
+                                Read from a replica
+                            </label>
+                        </>
+                    )}
+
+                    {(formData.mode === 'standalone' || formData.mode === 'cluster') && (
+                        <div className="flex gap-4">
+                            <div className="flex-1 space-y-2">
+                                <label className="text-base">{formData.mode === 'cluster' ? 'Seed Host' : 'Host'}</label>
+                                <div className="relative">
+                                    <Server className="absolute left-3 top-1/2 transform -translate-y-1/2 w-5 h-5 text-gray-500 dark:text-gray-400" />
+                                    <Input
+                                        name="host"
+                                        value={formData.host}
+                                        onChange={handleInputChange}
+                                        placeholder="127.0.0.1"
+                                        className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none pl-10 h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
+                                    />
+                                </div>
+                            </div>
+
+                            <div className="w-32 space-y-2">
+                                <label className="text-base">Port</label>
+                                <Input
+                                    name="port"
+                                    value={formData.port}
+                                    onChange={handleInputChange}
+                                    placeholder="6379"
+                                    className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
+                                />
+                            </div>
+                        </div>
+                    )}
 
                     <div className="flex gap-4">
                         <div className="flex-1 space-y-2">
@@ -280,6 +576,386 @@ export default function Connection() {
                         </div>
                     </div>
 
+                    <div className="flex gap-4">
+                        <div className="flex-1 space-y-2">
+                            <label className="text-base">Group</label>
+                            <Input
+                                name="group"
+                                value={formData.group}
+                                onChange={handleInputChange}
+                                placeholder="staging"
+                                className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
+                            />
+                        </div>
+
+                        <div className="flex-1 space-y-2">
+                            <label className="text-base">Tags</label>
+                            <Input
+                                name="tags"
+                                value={formData.tags}
+                                onChange={handleInputChange}
+                                placeholder="cache, eu-west"
+                                className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
+                            />
+                        </div>
+
+                        <div className="w-32 space-y-2">
+                            <label className="text-base">Color</label>
+                            <Input
+                                name="color"
+                                value={formData.color}
+                                onChange={handleInputChange}
+                                placeholder="#ef4444"
+                                className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
+                            />
+                        </div>
+                    </div>
+
+                    <div className="flex gap-4 items-end">
+                        <div className="flex-1 space-y-2">
+                            <label className="text-base">Environment</label>
+                            <Select
+                                value={formData.environment}
+                                onValueChange={(value) => setFormData(prev => ({
+                                    ...prev,
+                                    environment: value as Environment,
+                                    // Production starts out protected, it can still be switched off.
+                                    readOnly: value === 'prod' ? true : prev.readOnly
+                                }))}
+                            >
+                                <SelectTrigger className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10">
+                                    <SelectValue />
+                                </SelectTrigger>
+                                <SelectContent>
+                                    <SelectItem value="dev">Development</SelectItem>
+                                    <SelectItem value="staging">Staging</SelectItem>
+                                    <SelectItem value="prod">Production</SelectItem>
+                                </SelectContent>
+                            </Select>
+                        </div>
+
+                        <label className="flex items-center gap-2 h-10 text-base">
+                            <Switch
+                                checked={formData.readOnly}
+                                onCheckedChange={(checked) => setFormData(prev => ({ ...prev, readOnly: checked }))}
+                            />
+                            Read-only
+                        </label>
+                    </div>
+
+                    <div className="flex gap-4 items-end">
+                        <div className="flex-1 space-y-2">
+                            <label className="text-base">Key Delimiters</label>
+                            <Input
+                                name="keyDelimiters"
+                                value={formData.keyDelimiters}
+                                onChange={handleInputChange}
+                                disabled={formData.flatKeyList}
+                                placeholder=": / . |"
+                                className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10 text-gray-900 dark:text-white placeholder:text-gray-500 dark:placeholder:text-gray-400 focus:border-gray-400 dark:focus:border-gray-600"
+                            />
+                        </div>
+
+                        <label className="flex items-center gap-2 h-10 text-base">
+                            <Switch
+                                checked={formData.flatKeyList}
+                                onCheckedChange={(checked) => setFormData(prev => ({ ...prev, flatKeyList: checked }))}
+                            />
+                            Flat list
+                        </label>
+                    </div>
+
+                    {formData.mode !== 'unix' && (
+                        <div className="space-y-4">
+                            <label className="flex items-center gap-2 h-10 text-base">
+                                <Switch
+                                    checked={formData.tlsEnabled}
+                                    onCheckedChange={(checked) => setField('tlsEnabled', checked)}
+                                />
+                                TLS
+                            </label>
+
+                            {formData.tlsEnabled && (
+                                <>
+                                    <div className="space-y-2">
+                                        <label className="text-base">CA Certificate</label>
+                                        <Input
+                                            name="tlsCaCertPath"
+                                            value={formData.tlsCaCertPath}
+                                            onChange={handleInputChange}
+                                            placeholder="system trust store"
+                                            className={inputClassName}
+                                        />
+                                    </div>
+
+                                    <div className="flex gap-4">
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">Client Certificate</label>
+                                            <Input
+                                                name="tlsClientCertPath"
+                                                value={formData.tlsClientCertPath}
+                                                onChange={handleInputChange}
+                                                placeholder="client.crt"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">Client Key</label>
+                                            <Input
+                                                name="tlsClientKeyPath"
+                                                value={formData.tlsClientKeyPath}
+                                                onChange={handleInputChange}
+                                                placeholder="client.key"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+                                    </div>
+
+                                    {/* Only standalone connections can override the name or skip its check. */}
+                                    {formData.mode === 'standalone' && (
+                                        <div className="flex gap-4 items-end">
+                                            <div className="flex-1 space-y-2">
+                                                <label className="text-base">Server Name (SNI)</label>
+                                                <Input
+                                                    name="tlsServerName"
+                                                    value={formData.tlsServerName}
+                                                    onChange={handleInputChange}
+                                                    placeholder={formData.host || 'redis.example.com'}
+                                                    className={inputClassName}
+                                                />
+                                            </div>
+
+                                            <label className="flex items-center gap-2 h-10 text-base">
+                                                <Switch
+                                                    checked={formData.tlsSkipVerify}
+                                                    onCheckedChange={(checked) => setField('tlsSkipVerify', checked)}
+                                                />
+                                                Skip hostname check
+                                            </label>
+                                        </div>
+                                    )}
+                                </>
+                            )}
+                        </div>
+                    )}
+
+                    {/* Tunnels forward a single address, so they only apply to standalone servers. */}
+                    {formData.mode === 'standalone' && (
+                        <div className="space-y-4">
+                            <label className="flex items-center gap-2 h-10 text-base">
+                                <Switch
+                                    checked={formData.sshEnabled}
+                                    onCheckedChange={(checked) => setFormData(prev => ({
+                                        ...prev,
+                                        sshEnabled: checked,
+                                        // A connection uses an SSH tunnel or a proxy, not both.
+                                        proxyKind: checked ? 'none' : prev.proxyKind
+                                    }))}
+                                />
+                                SSH tunnel
+                            </label>
+
+                            {formData.sshEnabled && (
+                                <>
+                                    <div className="flex gap-4">
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">SSH Host</label>
+                                            <Input
+                                                name="sshHost"
+                                                value={formData.sshHost}
+                                                onChange={handleInputChange}
+                                                placeholder="bastion.example.com"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+
+                                        <div className="w-32 space-y-2">
+                                            <label className="text-base">SSH Port</label>
+                                            <Input
+                                                name="sshPort"
+                                                value={formData.sshPort}
+                                                onChange={handleInputChange}
+                                                placeholder="22"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+                                    </div>
+
+                                    <div className="flex gap-4">
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">SSH Username</label>
+                                            <Input
+                                                name="sshUsername"
+                                                value={formData.sshUsername}
+                                                onChange={handleInputChange}
+                                                placeholder="username"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">SSH Password</label>
+                                            <Input
+                                                name="sshPassword"
+                                                value={formData.sshPassword}
+                                                onChange={handleInputChange}
+                                                type="password"
+                                                placeholder="unused with a private key"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+                                    </div>
+
+                                    <div className="flex gap-4">
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">Private Key</label>
+                                            <Input
+                                                name="sshPrivateKeyPath"
+                                                value={formData.sshPrivateKeyPath}
+                                                onChange={handleInputChange}
+                                                placeholder="~/.ssh/id_ed25519"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">Passphrase</label>
+                                            <Input
+                                                name="sshPassphrase"
+                                                value={formData.sshPassphrase}
+                                                onChange={handleInputChange}
+                                                type="password"
+                                                placeholder="passphrase"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+                                    </div>
+
+                                    <div className="space-y-2">
+                                        <label className="text-base">Host Key Fingerprint</label>
+                                        <Input
+                                            name="sshHostKeyFingerprint"
+                                            value={formData.sshHostKeyFingerprint}
+                                            onChange={handleInputChange}
+                                            placeholder="SHA256:... (checked against ~/.ssh/known_hosts when empty)"
+                                            className={inputClassName}
+                                        />
+                                    </div>
+                                </>
+                            )}
+
+                            <div className="space-y-2">
+                                <label className="text-base">Proxy</label>
+                                <Select
+                                    value={formData.proxyKind}
+                                    onValueChange={(value) => setFormData(prev => ({
+                                        ...prev,
+                                        proxyKind: value as ProxyKind | 'none',
+                                        sshEnabled: value === 'none' ? prev.sshEnabled : false
+                                    }))}
+                                >
+                                    <SelectTrigger className="bg-gray-50 dark:bg-[#2c2c2c] border-gray-200 dark:border-none h-10">
+                                        <SelectValue />
+                                    </SelectTrigger>
+                                    <SelectContent>
+                                        <SelectItem value="none">None</SelectItem>
+                                        <SelectItem value="socks5">SOCKS5</SelectItem>
+                                        <SelectItem value="http">HTTP CONNECT</SelectItem>
+                                    </SelectContent>
+                                </Select>
+                            </div>
+
+                            {formData.proxyKind !== 'none' && (
+                                <>
+                                    <div className="flex gap-4">
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">Proxy Host</label>
+                                            <Input
+                                                name="proxyHost"
+                                                value={formData.proxyHost}
+                                                onChange={handleInputChange}
+                                                placeholder="proxy.example.com"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+
+                                        <div className="w-32 space-y-2">
+                                            <label className="text-base">Proxy Port</label>
+                                            <Input
+                                                name="proxyPort"
+                                                value={formData.proxyPort}
+                                                onChange={handleInputChange}
+                                                placeholder="1080"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+                                    </div>
+
+                                    <div className="flex gap-4">
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">Proxy Username</label>
+                                            <Input
+                                                name="proxyUsername"
+                                                value={formData.proxyUsername}
+                                                onChange={handleInputChange}
+                                                placeholder="username"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+
+                                        <div className="flex-1 space-y-2">
+                                            <label className="text-base">Proxy Password</label>
+                                            <Input
+                                                name="proxyPassword"
+                                                value={formData.proxyPassword}
+                                                onChange={handleInputChange}
+                                                type="password"
+                                                placeholder="password"
+                                                className={inputClassName}
+                                            />
+                                        </div>
+                                    </div>
+                                </>
+                            )}
+                        </div>
+                    )}
+
+                    <div className="flex gap-4">
+                        <div className="flex-1 space-y-2">
+                            <label className="text-base">Connect Timeout (s)</label>
+                            <Input
+                                name="connectTimeoutSecs"
+                                value={formData.connectTimeoutSecs}
+                                onChange={handleInputChange}
+                                placeholder="10"
+                                className={inputClassName}
+                            />
+                        </div>
+
+                        <div className="flex-1 space-y-2">
+                            <label className="text-base">Response Timeout (s)</label>
+                            <Input
+                                name="responseTimeoutSecs"
+                                value={formData.responseTimeoutSecs}
+                                onChange={handleInputChange}
+                                placeholder="none"
+                                className={inputClassName}
+                            />
+                        </div>
+
+                        <div className="flex-1 space-y-2">
+                            <label className="text-base">Keepalive (s)</label>
+                            <Input
+                                name="keepaliveSecs"
+                                value={formData.keepaliveSecs}
+                                onChange={handleInputChange}
+                                placeholder="15"
+                                className={inputClassName}
+                            />
+                        </div>
+                    </div>
+
                     <div className="flex justify-end gap-3 pt-12">
                         <Button
                             type="button"
@@ -326,11 +1002,17 @@ export default function Connection() {
             <CustomAlertDialog
                 open={deleteConfirmation.open}
                 title="Delete Connection"
-                description={`Are you sure you want to delete the connection "${deleteConfirmation.connectionName}"?`}
+                description={`Move the connection "${deleteConfirmation.connectionName}" to the trash? It can be restored from there.`}
                 onCancel={() => setDeleteConfirmation({ open: false, connectionName: '' })}
                 onContinue={handleConfirmedDelete}
             />
 
+            <TrashDialog
+                isOpen={isTrashOpen}
+                onClose={() => setIsTrashOpen(false)}
+                onRestored={loadConnections}
+            />
+
             <LoadingDialog 
                 open={isTestingConnection}
                 title="Testing Connection"
@@ -1,10 +1,12 @@
 'use client'
 
-import { Plus, RotateCw, Search, Clock, Users, Key, Database as DatabaseIcon, Split, Info } from 'lucide-react'
-import { useEffect, useState } from 'react'
+import { Plus, RotateCw, Search, Clock, Users, Key, Database as DatabaseIcon, Split, Info, X, FolderTree } from 'lucide-react'
+import { useEffect, useRef, useState } from 'react'
 import { redisCommands } from '@/services/redis-commands'
 import TreeView from '@/app/components/treeview';
 import { RedisTreeItem } from '@/models/redisTreeItem'
+import { ScanCursor, ScanPage } from '@/models/keyScan'
+import { mergeTrees } from '@/lib/key-tree'
 import { ScrollArea } from '@/components/ui/scroll-area'
 import RedisItemDetail from '../components/redis-item/redis-item-detail';
 import { toast } from 'sonner';
@@ -30,6 +32,13 @@ export default function Database() {
     const [searchTerm, setSearchTerm] = useState('')
     const [selectedItemName, setSelectedItemName] = useState<string>('');
     const [redisData, setRedisData] = useState<RedisTreeItem[]>([]);
+    const [scanCursor, setScanCursor] = useState<ScanCursor | null>(null);
+    const [scannedKeys, setScannedKeys] = useState(0);
+    const [isScanning, setIsScanning] = useState(false);
+    // Lists one folder level at a time instead of scanning every key up front.
+    const [browseByFolder, setBrowseByFolder] = useState(false);
+    // Progress events of older scans are ignored.
+    const scanIdRef = useRef<string | null>(null);
     const [isAddItemDialogOpen, setIsAddItemDialogOpen] = useState(false);
     const [serverStatistics, setServerStatistics] = useState<RedisServerStatistics | null>(null);
 
@@ -80,11 +89,22 @@ export default function Database() {
     }
 
     useEffect(() => {
-        redisCommands.getAllKeysAsTree().then((keys) => {
-            console.log("keys are: ", keys);
-            setRedisData(keys)
-        })
+        const unlisten = redisCommands.onScanProgress((event) => {
+            if (event.scanId === scanIdRef.current) {
+                setScannedKeys(prev => prev + event.keys.length);
+            }
+        });
+        loadKeys(true, false);
+
+        return () => {
+            unlisten.then((unlistenFn) => unlistenFn());
+            if (scanIdRef.current) {
+                redisCommands.cancelScan(scanIdRef.current);
+            }
+        };
+    }, [])
 
+    useEffect(() => {
         refreshServerStatistics();
 
         // Only set up interval if autoRefresh is enabled
@@ -101,7 +121,7 @@ export default function Database() {
 
     const handleDelete = (key: string) => {
         redisCommands.deleteKey(key).then(() => {
-            setRedisData(redisData.filter(item => item.key !== key));
+            setRedisData(redisData.filter(item => item.kind === 'folder' || item.key !== key));
             toast.success('Key deleted successfully');
         }).catch((error) => {
             toast.error('Failed to delete key: ' + error);
@@ -112,22 +132,72 @@ export default function Database() {
         setSelectedItemName(item.key);
     };
 
-    const handleRefresh = () => {
-        if (searchTerm) {
-            handleSearch();
-        } else {
-            redisCommands.getAllKeysAsTree().then((keys) => {
-                setRedisData(keys)
-                toast.success('Refreshed');
-            })
+    // Scans the next page of keys, or starts over when `reset` is set. With
+    // `browse`, only lists the top folder level.
+    const loadKeys = (reset: boolean, browse: boolean, notify = false) => {
+        if (scanIdRef.current) {
+            redisCommands.cancelScan(scanIdRef.current);
+        }
+        const scanId = crypto.randomUUID();
+        scanIdRef.current = scanId;
+        if (reset) {
+            setRedisData([]);
+            setScannedKeys(0);
+        }
+        setIsScanning(true);
+
+        const cursor = reset ? null : scanCursor;
+        const request = browse
+            ? redisCommands.getPrefixChildren(scanId, '', cursor)
+            : redisCommands.scanKeys(scanId, searchTerm, cursor);
+        request.then((page) => {
+            if (scanIdRef.current !== scanId) {
+                return;
+            }
+            setRedisData(prev => mergeTrees(prev, page.items));
+            setScanCursor(page.cursor);
+            if (notify) {
+                toast.success('Refreshed.');
+            }
+        }).catch((error) => {
+            toast.error('Failed to scan keys: ' + error);
+        }).finally(() => {
+            if (scanIdRef.current === scanId) {
+                scanIdRef.current = null;
+                setIsScanning(false);
+            }
+        });
+    }
+
+    const handleCancelScan = () => {
+        if (scanIdRef.current) {
+            redisCommands.cancelScan(scanIdRef.current);
         }
     }
 
+    const handleLoadChildren = (item: RedisTreeItem, cursor: ScanCursor | null) => {
+        return redisCommands.getPrefixChildren(crypto.randomUUID(), item.key, cursor).catch((error): ScanPage => {
+            toast.error('Failed to list keys: ' + error);
+            return { items: [], cursor: null, cancelled: false };
+        });
+    }
+
+    // Searching always scans, folders are only browsed without a search term.
+    const reload = (browse: boolean, notify: boolean) => {
+        loadKeys(true, browse && !searchTerm, notify);
+    }
+
+    const handleToggleBrowse = () => {
+        setBrowseByFolder(!browseByFolder);
+        reload(!browseByFolder, false);
+    }
+
+    const handleRefresh = () => {
+        reload(browseByFolder, true);
+    }
+
     const handleSearch = () => {
-        redisCommands.searchKeysAsTree(searchTerm).then((keys) => {
-            setRedisData(keys)
-            toast.success('Refreshed.');
-        })
+        reload(browseByFolder, true);
     }
 
     const handleStatisticsRefresh = () => {
@@ -198,6 +268,14 @@ export default function Database() {
                                 />
                             </div>
 
+                            <Button
+                                className={`w-8 h-8 tadis-button ${browseByFolder ? 'ring-2 ring-gray-500' : ''}`}
+                                variant="secondary"
+                                title="Browse by folder"
+                                onClick={handleToggleBrowse}
+                            >
+                                <FolderTree strokeWidth={2.0} />
+                            </Button>
                             <Button className="w-8 h-8 tadis-button" variant="secondary" onClick={handleRefresh}>
                                 <RotateCw strokeWidth={2.0} />
                             </Button>
@@ -207,8 +285,17 @@ export default function Database() {
                         </div>
 
                         {/* Keys List Header */}
-                        <div className="text-sm text-gray-800 dark:text-gray-400 mb-2">
-                            KEYS ({redisData.length} SCANNED)
+                        <div className="flex items-center justify-between text-sm text-gray-800 dark:text-gray-400 mb-2">
+                            <span>KEYS ({scannedKeys} SCANNED)</span>
+                            {isScanning ? (
+                                <button className="flex items-center gap-1 hover:text-gray-600 dark:hover:text-gray-200" onClick={handleCancelScan}>
+                                    <X className="w-3 h-3" /> Stop
+                                </button>
+                            ) : scanCursor && (
+                                <button className="hover:text-gray-600 dark:hover:text-gray-200" onClick={() => loadKeys(false, browseByFolder && !searchTerm)}>
+                                    Load more
+                                </button>
+                            )}
                         </div>
 
                         {/* Wrap the TreeView in a ScrollArea */}
@@ -216,11 +303,12 @@ export default function Database() {
                             <div className="space-y-1 pr-4">
                                 {redisData.map((item) => (
                                     <TreeView
-                                        key={item.label}
+                                        key={`${item.kind}:${item.key}`}
                                         item={item}
                                         onDelete={handleDelete}
                                         selectedItemName={selectedItemName}
                                         onItemSelect={handleItemSelect}
+                                        onLoadChildren={handleLoadChildren}
                                     />
                                 ))}
                             </div>
@@ -0,0 +1,48 @@
+import { RedisTreeItem } from '@/models/redisTreeItem';
+
+// Adds the items of another scan page to the tree, merging folders by prefix,
+// as the same label may follow different delimiters.
+export const mergeTrees = (tree: RedisTreeItem[], page: RedisTreeItem[]): RedisTreeItem[] => {
+    const merged = [...tree];
+    for (const item of page) {
+        const index = merged.findIndex(existing => existing.key === item.key && existing.kind === item.kind);
+        if (index === -1) {
+            merged.push(item);
+        } else if (item.kind === 'folder') {
+            merged[index] = mergeFolders(merged[index], item);
+        }
+        // Otherwise a key was found again, SCAN may return a key more than once.
+    }
+    return merged.sort((a, b) => {
+        if (a.kind !== b.kind) {
+            return a.kind === 'folder' ? -1 : 1;
+        }
+        return a.label.localeCompare(b.label);
+    });
+}
+
+// Folders listed by prefix only carry the totals of their page, which add up.
+// Scanned folders are totalled from their merged children instead, so a key
+// found by two pages counts once.
+const mergeFolders = (existing: RedisTreeItem, item: RedisTreeItem): RedisTreeItem => {
+    if (existing.children === undefined || item.children === undefined) {
+        return {
+            ...existing,
+            key_count: (existing.key_count ?? 0) + (item.key_count ?? 0),
+            memory_usage: existing.memory_usage === undefined && item.memory_usage === undefined
+                ? undefined
+                : (existing.memory_usage ?? 0) + (item.memory_usage ?? 0),
+        };
+    }
+
+    const children = mergeTrees(existing.children, item.children);
+    let key_count = 0;
+    let memory_usage: number | undefined = undefined;
+    for (const child of children) {
+        key_count += child.kind === 'folder' ? child.key_count ?? 0 : 1;
+        if (child.memory_usage !== undefined) {
+            memory_usage = (memory_usage ?? 0) + child.memory_usage;
+        }
+    }
+    return { ...existing, children, key_count, memory_usage };
+}
@@ -1,7 +1,106 @@
 export interface ConnectionConfig {
+    id?: number | null;
     name: string;
     host: string;
     port: number;
     username: string;
     password: string;
+    database?: number;
+    tlsEnabled?: boolean;
+    tlsCaCertPath?: string | null;
+    tlsClientCertPath?: string | null;
+    tlsClientKeyPath?: string | null;
+    tlsSkipVerify?: boolean;
+    tlsServerName?: string | null;
+    ssh?: SshConfig | null;
+    proxy?: ProxyConfig | null;
+    mode?: ConnectionMode;
+    sentinel?: SentinelConfig | null;
+    socketPath?: string | null;
+    resp3?: boolean;
+    connectTimeoutSecs?: number | null;
+    responseTimeoutSecs?: number | null;
+    keepaliveSecs?: number | null;
+    clientName?: string | null;
+    group?: string | null;
+    tags?: string[];
+    color?: string | null;
+    sortOrder?: number;
+    readOnly?: boolean;
+    environment?: Environment;
+    // Separators between key tree folders, ':' when empty.
+    keyDelimiters?: string[];
+    flatKeyList?: boolean;
+    createdAt?: string | null;
+    updatedAt?: string | null;
+    deletedAt?: string | null;
+    lastConnectedAt?: string | null;
 }
+
+export type ConnectionMode = 'standalone' | 'sentinel' | 'cluster' | 'unix';
+
+export type Environment = 'dev' | 'staging' | 'prod';
+
+export type ProxyKind = 'socks5' | 'http';
+
+export interface ProxyConfig {
+    kind: ProxyKind;
+    host: string;
+    port: number;
+    username?: string | null;
+    password?: string | null;
+}
+
+export interface SshConfig {
+    host: string;
+    port: number;
+    username: string;
+    password?: string | null;
+    privateKeyPath?: string | null;
+    passphrase?: string | null;
+    hostKeyFingerprint?: string | null;
+}
+
+export interface SentinelConfig {
+    masterName: string;
+    nodes: string[];
+    username?: string | null;
+    password?: string | null;
+    readFromReplica?: boolean;
+}
+
+export interface ActiveConnection {
+    name: string;
+    database: number;
+    cluster: boolean;
+    readOnly: boolean;
+    environment: Environment;
+    selected: boolean;
+}
+
+export interface ConnectionStatusEvent {
+    name: string;
+    error?: string;
+}
+
+export interface CredentialsStatus {
+    masterPassword: boolean;
+    locked: boolean;
+    error: string | null;
+}
+
+export interface ImportReport {
+    imported: string[];
+    overwritten: string[];
+    conflicts: string[];
+    failed: ImportFailure[];
+}
+
+export interface ImportFailure {
+    entry: number;
+    error: string;
+}
+
+export type MergeStrategy = 'skip' | 'overwrite' | 'rename';
+
+export type ExportFormat = 'json' | 'toml';
@@ -0,0 +1,19 @@
+import { RedisTreeItem } from './redisTreeItem';
+
+export interface ScanCursor {
+    nodes: string[];
+    cursor: number;
+}
+
+export interface ScanPage {
+    items: RedisTreeItem[];
+    cursor: ScanCursor | null;
+    cancelled: boolean;
+}
+
+export interface ScanProgressEvent {
+    scanId: string;
+    keys: string[];
+    found: number;
+    cursor: ScanCursor | null;
+}
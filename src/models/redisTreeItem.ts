@@ -1,8 +1,19 @@
-
 export type RedisItemType = 'string' | 'set' | 'hash' | 'list' | 'stream' | 'zset';
+export type RedisTreeItemKind = 'folder' | 'key';
 export interface RedisTreeItem {
+    // For folders, the prefix shared by every key inside.
     key: string;
     label: string;
+    kind: RedisTreeItemKind;
+    // Unset on folders listed by prefix, whose children load when expanded.
     children?: RedisTreeItem[];
+    // Empty on folders.
     item_type: RedisItemType;
+    slot?: number;
+    node?: string;
+    ttl?: number;
+    // Summed over the keys inside a folder.
+    memory_usage?: number;
+    // Keys inside a folder, at any depth.
+    key_count?: number;
 }
@@ -1,12 +1,37 @@
 
-import { ConnectionConfig } from '@/models/connection';
+import {
+  ActiveConnection,
+  ConnectionConfig,
+  ConnectionStatusEvent,
+  CredentialsStatus,
+  ExportFormat,
+  ImportReport,
+  MergeStrategy,
+} from '@/models/connection';
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 
 const LOAD_CONFIG_COMMAND_NAME = 'load_connection_config';
 const SAVE_CONFIG_COMMAND_NAME = 'save_connection_config';
 const DELETE_CONFIG_COMMAND_NAME = 'delete_connection_config';
+const LOAD_DELETED_CONFIGS_COMMAND_NAME = 'load_deleted_connection_configs';
+const RESTORE_CONFIG_COMMAND_NAME = 'restore_connection_config';
+const PURGE_CONFIGS_COMMAND_NAME = 'purge_connection_configs';
+const REORDER_CONFIGS_COMMAND_NAME = 'reorder_connection_configs';
 const TEST_CONNECTION_COMMAND_NAME = 'test_connection';
 const CONNECT_TO_REDIS_COMMAND_NAME = 'connect_to_redis';
+const SELECT_DATABASE_COMMAND_NAME = 'select_database';
+const DISCONNECT_COMMAND_NAME = 'disconnect';
+const SWITCH_CONNECTION_COMMAND_NAME = 'switch_connection';
+const LIST_ACTIVE_CONNECTIONS_COMMAND_NAME = 'list_active_connections';
+const IMPORT_CONNECTIONS_COMMAND_NAME = 'import_connections';
+const EXPORT_CONNECTIONS_COMMAND_NAME = 'export_connections';
+const GET_CREDENTIALS_STATUS_COMMAND_NAME = 'get_credentials_status';
+const UNLOCK_CREDENTIALS_COMMAND_NAME = 'unlock_credentials';
+const SET_MASTER_PASSWORD_COMMAND_NAME = 'set_master_password';
+
+const CONNECTION_LOST_EVENT_NAME = 'connection-lost';
+const CONNECTION_RESTORED_EVENT_NAME = 'connection-restored';
 
 export const connectionCommands = {
 
@@ -14,20 +39,85 @@ export const connectionCommands = {
     return invoke<ConnectionConfig[]>(LOAD_CONFIG_COMMAND_NAME);
   },
 
-  saveConfig: async (config: ConnectionConfig, isNew: boolean): Promise<void> => {
-    return invoke(SAVE_CONFIG_COMMAND_NAME, { config, isNew });
+  saveConfig: async (config: ConnectionConfig, isNew: boolean): Promise<number> => {
+    return invoke<number>(SAVE_CONFIG_COMMAND_NAME, { config, isNew });
   },
 
   deleteConfig: async (connectionName: string): Promise<void> => {
     return invoke(DELETE_CONFIG_COMMAND_NAME, { connectionName });
   },
 
+  loadDeletedConfigs: async (): Promise<ConnectionConfig[]> => {
+    return invoke<ConnectionConfig[]>(LOAD_DELETED_CONFIGS_COMMAND_NAME);
+  },
+
+  restoreConfig: async (connectionId: number): Promise<void> => {
+    return invoke(RESTORE_CONFIG_COMMAND_NAME, { connectionId });
+  },
+
+  // Empties the whole trash when no id is given.
+  purgeConfigs: async (connectionId?: number): Promise<void> => {
+    return invoke(PURGE_CONFIGS_COMMAND_NAME, { connectionId: connectionId ?? null });
+  },
+
+  reorderConfigs: async (names: string[]): Promise<void> => {
+    return invoke(REORDER_CONFIGS_COMMAND_NAME, { names });
+  },
+
   testConnection: async (config: ConnectionConfig): Promise<void> => {
     return invoke(TEST_CONNECTION_COMMAND_NAME, { config });
   },
 
   connectToRedis: async (config: ConnectionConfig): Promise<void> => {
     return invoke(CONNECT_TO_REDIS_COMMAND_NAME, { config });
+  },
+
+  selectDatabase: async (database: number, connectionId?: string): Promise<void> => {
+    return invoke(SELECT_DATABASE_COMMAND_NAME, { database, connectionId });
+  },
+
+  disconnect: async (connectionName: string): Promise<void> => {
+    return invoke(DISCONNECT_COMMAND_NAME, { connectionName });
+  },
+
+  switchConnection: async (connectionName: string): Promise<void> => {
+    return invoke(SWITCH_CONNECTION_COMMAND_NAME, { connectionName });
+  },
+
+  listActiveConnections: async (): Promise<ActiveConnection[]> => {
+    return invoke<ActiveConnection[]>(LIST_ACTIVE_CONNECTIONS_COMMAND_NAME);
+  },
+
+  importConnections: async (
+    source: string,
+    strategy: MergeStrategy = 'skip',
+    passphrase?: string
+  ): Promise<ImportReport> => {
+    return invoke<ImportReport>(IMPORT_CONNECTIONS_COMMAND_NAME, { source, strategy, passphrase });
+  },
+
+  exportConnections: async (names: string[], format: ExportFormat, passphrase?: string): Promise<string> => {
+    return invoke<string>(EXPORT_CONNECTIONS_COMMAND_NAME, { names, format, passphrase });
+  },
+
+  getCredentialsStatus: async (): Promise<CredentialsStatus> => {
+    return invoke<CredentialsStatus>(GET_CREDENTIALS_STATUS_COMMAND_NAME);
+  },
+
+  unlockCredentials: async (masterPassword: string): Promise<void> => {
+    return invoke(UNLOCK_CREDENTIALS_COMMAND_NAME, { masterPassword });
+  },
+
+  setMasterPassword: async (masterPassword: string | null): Promise<void> => {
+    return invoke(SET_MASTER_PASSWORD_COMMAND_NAME, { masterPassword });
+  },
+
+  onConnectionLost: async (handler: (event: ConnectionStatusEvent) => void): Promise<UnlistenFn> => {
+    return listen<ConnectionStatusEvent>(CONNECTION_LOST_EVENT_NAME, (event) => handler(event.payload));
+  },
+
+  onConnectionRestored: async (handler: (event: ConnectionStatusEvent) => void): Promise<UnlistenFn> => {
+    return listen<ConnectionStatusEvent>(CONNECTION_RESTORED_EVENT_NAME, (event) => handler(event.payload));
   }
 
 };
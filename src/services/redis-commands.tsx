@@ -1,8 +1,10 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import { RedisTreeItem } from '@/models/redisTreeItem';
 import { RedisDetailItem } from '@/types/redisItem';
 import { RedisServerStatistics } from '@/models/redisServerStatistics';
 import { RedisClientInfo } from '@/models/redisClientInfo';
+import { ScanCursor, ScanPage, ScanProgressEvent } from '@/models/keyScan';
 
 const GET_ALL_KEYS_AS_TREE_COMMAND_NAME = 'get_all_keys_as_tree';
 const SEARCH_KEYS_AS_TREE_COMMAND_NAME = 'search_keys_as_tree';
@@ -30,111 +32,132 @@ const ADD_LIST_COMMAND_NAME = 'add_list';
 const ADD_ZSET_ITEMS_COMMAND_NAME = 'add_zset_items';
 const GET_SERVER_STATISTICS_COMMAND_NAME = 'get_server_statistics';
 const GET_CLIENT_LIST_COMMAND_NAME = 'get_client_list';
+const SCAN_KEYS_COMMAND_NAME = 'scan_keys';
+const CANCEL_SCAN_COMMAND_NAME = 'cancel_scan';
+const GET_PREFIX_CHILDREN_COMMAND_NAME = 'get_prefix_children';
+
+const SCAN_PROGRESS_EVENT_NAME = 'scan-progress';
 
 export const redisCommands = {
 
-    getAllKeysAsTree: async (): Promise<RedisTreeItem[]> => {
-        return invoke<RedisTreeItem[]>(GET_ALL_KEYS_AS_TREE_COMMAND_NAME);
+    getAllKeysAsTree: async (connectionId?: string): Promise<RedisTreeItem[]> => {
+        return invoke<RedisTreeItem[]>(GET_ALL_KEYS_AS_TREE_COMMAND_NAME, { connectionId });
+    },
+
+    searchKeysAsTree: async (searchTerm: string, connectionId?: string): Promise<RedisTreeItem[]> => {
+        return invoke<RedisTreeItem[]>(SEARCH_KEYS_AS_TREE_COMMAND_NAME, { searchTerm, connectionId });
+    },
+
+    scanKeys: async (scanId: string, searchTerm?: string, cursor?: ScanCursor | null, details?: boolean, connectionId?: string): Promise<ScanPage> => {
+        return invoke<ScanPage>(SCAN_KEYS_COMMAND_NAME, { scanId, searchTerm, cursor, details, connectionId });
+    },
+
+    cancelScan: async (scanId: string): Promise<void> => {
+        return invoke<void>(CANCEL_SCAN_COMMAND_NAME, { scanId });
+    },
+
+    getPrefixChildren: async (scanId: string, prefix: string, cursor?: ScanCursor | null, connectionId?: string): Promise<ScanPage> => {
+        return invoke<ScanPage>(GET_PREFIX_CHILDREN_COMMAND_NAME, { scanId, prefix, cursor, connectionId });
     },
 
-    searchKeysAsTree: async (searchTerm: string): Promise<RedisTreeItem[]> => {
-        return invoke<RedisTreeItem[]>(SEARCH_KEYS_AS_TREE_COMMAND_NAME, { searchTerm });
+    onScanProgress: async (handler: (event: ScanProgressEvent) => void): Promise<UnlistenFn> => {
+        return listen<ScanProgressEvent>(SCAN_PROGRESS_EVENT_NAME, (event) => handler(event.payload));
     },
 
-    getKeyDetail: async (key: string): Promise<RedisDetailItem> => {
-        return invoke<RedisDetailItem>(GET_KEY_DETAIL_COMMAND_NAME, { key });
+    getKeyDetail: async (key: string, connectionId?: string): Promise<RedisDetailItem> => {
+        return invoke<RedisDetailItem>(GET_KEY_DETAIL_COMMAND_NAME, { key, connectionId });
     },
 
-    saveString: async (key: string, value: string, ttl: number | null): Promise<void> => {
-        return invoke<void>(SAVE_STRING_COMMAND_NAME, { key, value, ttl });
+    saveString: async (key: string, value: string, ttl: number | null, connectionId?: string): Promise<void> => {
+        return invoke<void>(SAVE_STRING_COMMAND_NAME, { key, value, ttl, connectionId });
     },
 
-    updateTTL: async (key: string, ttl: number): Promise<void> => {
-        return invoke<void>(UPDATE_TTL_COMMAND_NAME, { key, ttl });
+    updateTTL: async (key: string, ttl: number, connectionId?: string): Promise<void> => {
+        return invoke<void>(UPDATE_TTL_COMMAND_NAME, { key, ttl, connectionId });
     },
 
-    listAddItems: async (key: string, items: string[], direction: 'Start' | 'End'): Promise<void> => {
-        return invoke<void>(LIST_ADD_ITEMS_COMMAND_NAME, { key, items, direction });
+    listAddItems: async (key: string, items: string[], direction: 'Start' | 'End', connectionId?: string): Promise<void> => {
+        return invoke<void>(LIST_ADD_ITEMS_COMMAND_NAME, { key, items, direction, connectionId });
     },
 
-    setAddItems: async (key: string, items: string[], ttl: number | null): Promise<void> => {
-        return invoke<void>(SET_ADD_ITEMS_COMMAND_NAME, { key, items, ttl });
+    setAddItems: async (key: string, items: string[], ttl: number | null, connectionId?: string): Promise<void> => {
+        return invoke<void>(SET_ADD_ITEMS_COMMAND_NAME, { key, items, ttl, connectionId });
     },
 
-    hashAddItems: async (key: string, items: [string, string][], ttl: number | null): Promise<void> => {
-        return invoke<void>(HASH_ADD_ITEMS_COMMAND_NAME, { key, items, ttl });
+    hashAddItems: async (key: string, items: [string, string][], ttl: number | null, connectionId?: string): Promise<void> => {
+        return invoke<void>(HASH_ADD_ITEMS_COMMAND_NAME, { key, items, ttl, connectionId });
     },
 
-    zsetAddItems: async (key: string, items: [number, string][], replace: boolean): Promise<void> => {
-        return invoke<void>(ZSET_ADD_ITEMS_COMMAND_NAME, { key, items, replace });
+    zsetAddItems: async (key: string, items: [number, string][], replace: boolean, connectionId?: string): Promise<void> => {
+        return invoke<void>(ZSET_ADD_ITEMS_COMMAND_NAME, { key, items, replace, connectionId });
     },
 
-    streamAddItems: async (key: string, id: string, items: [string, string][], ttl: number | null): Promise<void> => {
-        return invoke<void>(STREAM_ADD_ITEMS_COMMAND_NAME, { key, id, items, ttl });
+    streamAddItems: async (key: string, id: string, items: [string, string][], ttl: number | null, connectionId?: string): Promise<void> => {
+        return invoke<void>(STREAM_ADD_ITEMS_COMMAND_NAME, { key, id, items, ttl, connectionId });
     },
 
-    deleteKey: async (key: string): Promise<void> => {
-        return invoke<void>(DELETE_KEY_COMMAND_NAME, { key });
+    deleteKey: async (key: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(DELETE_KEY_COMMAND_NAME, { key, connectionId });
     },
 
-    hashDeleteField: async (key: string, field: string): Promise<void> => {
-        return invoke<void>(HASH_DELETE_FIELD_COMMAND_NAME, { key, field });
+    hashDeleteField: async (key: string, field: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(HASH_DELETE_FIELD_COMMAND_NAME, { key, field, connectionId });
     },
 
-    setDeleteValue: async (key: string, value: string): Promise<void> => {
-        return invoke<void>(SET_DELETE_VALUE_COMMAND_NAME, { key, value });
+    setDeleteValue: async (key: string, value: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(SET_DELETE_VALUE_COMMAND_NAME, { key, value, connectionId });
     },
 
-    zsetDeleteValue: async (key: string, value: string): Promise<void> => {
-        return invoke<void>(ZSET_DELETE_VALUE_COMMAND_NAME, { key, value });
+    zsetDeleteValue: async (key: string, value: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(ZSET_DELETE_VALUE_COMMAND_NAME, { key, value, connectionId });
     },
 
-    streamDeleteValue: async (key: string, id: string): Promise<void> => {
-        return invoke<void>(STREAM_DELETE_VALUE_COMMAND_NAME, { key, id });
+    streamDeleteValue: async (key: string, id: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(STREAM_DELETE_VALUE_COMMAND_NAME, { key, id, connectionId });
     },
 
-    listDeleteValue: async (key: string, index: number): Promise<void> => {
-        return invoke<void>(LIST_DELETE_VALUE_COMMAND_NAME, { key, index });
+    listDeleteValue: async (key: string, index: number, connectionId?: string): Promise<void> => {
+        return invoke<void>(LIST_DELETE_VALUE_COMMAND_NAME, { key, index, connectionId });
     },
 
-    listUpdateValue: async (key: string, index: number, value: string): Promise<void> => {
-        return invoke<void>(LIST_UPDATE_VALUE_COMMAND_NAME, { key, index, value });
+    listUpdateValue: async (key: string, index: number, value: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(LIST_UPDATE_VALUE_COMMAND_NAME, { key, index, value, connectionId });
     },
 
-    setUpdateValue: async (key: string, value: string, newValue: string): Promise<void> => {
-        return invoke<void>(SET_UPDATE_VALUE_COMMAND_NAME, { key, value, newValue });
+    setUpdateValue: async (key: string, value: string, newValue: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(SET_UPDATE_VALUE_COMMAND_NAME, { key, value, newValue, connectionId });
     },
 
-    hashUpdateValue: async (key: string, field: string, value: string): Promise<void> => {
-        return invoke<void>(HASH_UPDATE_VALUE_COMMAND_NAME, { key, field, value });
+    hashUpdateValue: async (key: string, field: string, value: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(HASH_UPDATE_VALUE_COMMAND_NAME, { key, field, value, connectionId });
     },
 
-    hashUpdateField: async (key: string, oldField: string, newField: string, value: string): Promise<void> => {
-        return invoke<void>(HASH_UPDATE_FIELD_COMMAND_NAME, { key, oldField, newField, value });
+    hashUpdateField: async (key: string, oldField: string, newField: string, value: string, connectionId?: string): Promise<void> => {
+        return invoke<void>(HASH_UPDATE_FIELD_COMMAND_NAME, { key, oldField, newField, value, connectionId });
     },
 
-    zsetUpdateScore: async (key: string, member: string, score: number): Promise<void> => {
-        return invoke<void>(ZSET_UPDATE_SCORE_COMMAND_NAME, { key, member, score });
+    zsetUpdateScore: async (key: string, member: string, score: number, connectionId?: string): Promise<void> => {
+        return invoke<void>(ZSET_UPDATE_SCORE_COMMAND_NAME, { key, member, score, connectionId });
     },
 
-    zsetUpdateMember: async (key: string, oldMember: string, newMember: string, score: number): Promise<void> => {
-        return invoke<void>(ZSET_UPDATE_MEMBER_COMMAND_NAME, { key, oldMember, newMember, score });
+    zsetUpdateMember: async (key: string, oldMember: string, newMember: string, score: number, connectionId?: string): Promise<void> => {
+        return invoke<void>(ZSET_UPDATE_MEMBER_COMMAND_NAME, { key, oldMember, newMember, score, connectionId });
     },
 
-    addList: async (key: string, items: string[], ttl: number): Promise<void> => {
-        return invoke<void>(ADD_LIST_COMMAND_NAME, { key, items, ttl });
+    addList: async (key: string, items: string[], ttl: number, connectionId?: string): Promise<void> => {
+        return invoke<void>(ADD_LIST_COMMAND_NAME, { key, items, ttl, connectionId });
     },
 
-    addZsetItems: async (key: string, items: [number, string][], ttl: number | null): Promise<void> => {
-        return invoke<void>(ADD_ZSET_ITEMS_COMMAND_NAME, { key, items, ttl });
+    addZsetItems: async (key: string, items: [number, string][], ttl: number | null, connectionId?: string): Promise<void> => {
+        return invoke<void>(ADD_ZSET_ITEMS_COMMAND_NAME, { key, items, ttl, connectionId });
     },
 
-    getServerStatistics: async (): Promise<RedisServerStatistics> => {
-        return invoke<RedisServerStatistics>(GET_SERVER_STATISTICS_COMMAND_NAME);
+    getServerStatistics: async (connectionId?: string): Promise<RedisServerStatistics> => {
+        return invoke<RedisServerStatistics>(GET_SERVER_STATISTICS_COMMAND_NAME, { connectionId });
     },
 
-    getClientList: async (): Promise<RedisClientInfo[]> => {
-        return invoke<RedisClientInfo[]>(GET_CLIENT_LIST_COMMAND_NAME);
+    getClientList: async (connectionId?: string): Promise<RedisClientInfo[]> => {
+        return invoke<RedisClientInfo[]>(GET_CLIENT_LIST_COMMAND_NAME, { connectionId });
     },
 };
 